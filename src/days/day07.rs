@@ -1,4 +1,5 @@
 use crate::solution::{Solution, SolvedValue};
+use crate::utils::math;
 use rayon::prelude::*;
 
 fn parse_equations(input: &str) -> Vec<(usize, Vec<usize>)> {
@@ -44,7 +45,7 @@ impl Operator {
         match self {
             Operator::Add => a + b,
             Operator::Multiply => a * b,
-            Operator::Concat => a * (10_usize).pow(b.ilog10() + 1) + b,
+            Operator::Concat => math::concat(a, b),
         }
     }
 }
@@ -2,6 +2,7 @@ use rayon::prelude::*;
 use std::collections::HashMap;
 
 use crate::solution::{Solution, SolvedValue};
+use crate::utils::math;
 
 #[derive(Debug, Clone, Copy)]
 struct Stone(usize);
@@ -16,11 +17,8 @@ impl Stone {
             }
             let count = if self.0 == 0 {
                 Stone(1).blink(n - 1, cache)
-            } else if self.0.ilog10() % 2 == 1 {
-                let factor = 10usize.pow((self.0.ilog10() + 1) / 2);
-                let left = Stone(self.0 / factor);
-                let right = Stone(self.0 - left.0 * factor);
-                left.blink(n - 1, cache) + right.blink(n - 1, cache)
+            } else if let Some((left, right)) = math::split_digits(self.0) {
+                Stone(left).blink(n - 1, cache) + Stone(right).blink(n - 1, cache)
             } else {
                 Stone(self.0 * 2024).blink(n - 1, cache)
             };
@@ -1,64 +1,232 @@
 #![allow(clippy::cast_possible_truncation)]
 #![allow(clippy::cast_sign_loss)]
-use std::str::FromStr;
-extern crate nalgebra;
-use nalgebra::{Matrix2, Vector2};
 use regex::Regex;
+use std::str::FromStr;
 
 use crate::solution::{Solution, SolvedValue};
+use crate::utils::math::{self, LinearSolution};
+
+const COST_A: isize = 3;
+const COST_B: isize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Button {
+    vec: (isize, isize),
+    cost: isize,
+    limit: Option<isize>,
+}
+
+impl Button {
+    fn allows(&self, presses: isize) -> bool {
+        presses >= 0 && self.limit.is_none_or(|limit| presses <= limit)
+    }
 
-#[derive(Debug, Clone, Copy)]
+    /// Most presses possible before overshooting `remaining` or the limit.
+    fn max_presses(&self, remaining: (isize, isize)) -> isize {
+        let by_x = (self.vec.0 > 0).then(|| remaining.0 / self.vec.0);
+        let by_y = (self.vec.1 > 0).then(|| remaining.1 / self.vec.1);
+        match (by_x, by_y) {
+            (Some(x), Some(y)) => x.min(y),
+            (Some(presses), None) | (None, Some(presses)) => presses,
+            // A button that doesn't move the claw is never worth pressing
+            (None, None) => 0,
+        }
+        .min(self.limit.unwrap_or(isize::MAX))
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Machine {
-    button_a_vec: (f64, f64),
-    button_b_vec: (f64, f64),
-    price_location: (f64, f64),
+    buttons: Vec<Button>,
+    price_location: (isize, isize),
 }
 
 impl Machine {
-    fn steps_to_price(&self) -> (usize, usize) {
-        let equations = Matrix2::new(
-            self.button_a_vec.0,
-            self.button_b_vec.0,
-            self.button_a_vec.1,
-            self.button_b_vec.1,
-        );
-        let solutions = Vector2::new(self.price_location.0, self.price_location.1);
-        let decomp = equations.lu();
-        if let Some(res) = decomp.solve(&solutions) {
-            let rounded_x = res.x.round();
-            let rounded_y = res.y.round();
-            if (rounded_x - res.x).abs() <= 0.001 && (rounded_y - res.y).abs() <= 0.001 {
-                return (rounded_x as usize, rounded_y as usize);
+    fn with_default_limit(mut self, limit: isize) -> Self {
+        for button in &mut self.buttons {
+            button.limit.get_or_insert(limit);
+        }
+        self
+    }
+
+    /// Number of presses per button for the cheapest way to reach the price.
+    fn steps_to_price(&self) -> Option<Vec<isize>> {
+        match self.buttons.as_slice() {
+            [] => (self.price_location == (0, 0)).then(Vec::new),
+            [button] => {
+                let presses = single_steps(button, self.price_location)?;
+                Some(vec![presses])
+            }
+            [a, b] => {
+                let (a, b) = pair_steps(a, b, self.price_location)?;
+                Some(vec![a, b])
+            }
+            _ => {
+                let mut search = Search {
+                    buttons: &self.buttons,
+                    presses: vec![0; self.buttons.len()],
+                    best: None,
+                };
+                search.branch(0, self.price_location, 0);
+                search.best.map(|(_, presses)| presses)
             }
         }
-        (0, 0)
     }
 
-    fn min_tokens_price(&self) -> usize {
-        let presses = self.steps_to_price();
-        presses.0 * 3 + presses.1
+    fn min_tokens_price(&self) -> Option<usize> {
+        self.steps_to_price().map(|presses| {
+            presses
+                .iter()
+                .zip(&self.buttons)
+                .map(|(presses, button)| presses * button.cost)
+                .sum::<isize>() as usize
+        })
+    }
+}
+
+fn single_steps(button: &Button, target: (isize, isize)) -> Option<isize> {
+    let presses = if button.vec.0 != 0 {
+        target.0 / button.vec.0
+    } else if button.vec.1 != 0 {
+        target.1 / button.vec.1
+    } else {
+        0
+    };
+    (button.allows(presses)
+        && presses * button.vec.0 == target.0
+        && presses * button.vec.1 == target.1)
+        .then_some(presses)
+}
+
+fn pair_steps(a: &Button, b: &Button, target: (isize, isize)) -> Option<(isize, isize)> {
+    let equations = [
+        [a.vec.0 as i128, b.vec.0 as i128],
+        [a.vec.1 as i128, b.vec.1 as i128],
+    ];
+    let solutions = [target.0 as i128, target.1 as i128];
+    match math::solve_2x2(equations, solutions) {
+        LinearSolution::Unique(presses_a, presses_b) => {
+            let (presses_a, presses_b) = (presses_a as isize, presses_b as isize);
+            (a.allows(presses_a) && b.allows(presses_b)).then_some((presses_a, presses_b))
+        }
+        LinearSolution::NotIntegral => None,
+        LinearSolution::Singular => collinear_pair_steps(a, b, target),
+    }
+}
+
+/// Both buttons move in the same direction, so the cheapest combination
+/// along one axis is the cheapest overall (if it also hits the other axis).
+fn collinear_pair_steps(a: &Button, b: &Button, target: (isize, isize)) -> Option<(isize, isize)> {
+    let costs = (a.cost, b.cost);
+    let limits = (a.limit, b.limit);
+    let (presses_a, presses_b) = if a.vec.0 != 0 || b.vec.0 != 0 {
+        math::cheapest_combination(a.vec.0, b.vec.0, target.0, costs, limits)?
+    } else {
+        math::cheapest_combination(a.vec.1, b.vec.1, target.1, costs, limits)?
+    };
+    (presses_a * a.vec.0 + presses_b * b.vec.0 == target.0
+        && presses_a * a.vec.1 + presses_b * b.vec.1 == target.1)
+        .then_some((presses_a, presses_b))
+}
+
+/// Branch and bound over the presses of all but the last two buttons, which
+/// are then solved exactly.
+///
+/// This is exponential in the number of buttons and only meant for small
+/// machines.
+struct Search<'a> {
+    buttons: &'a [Button],
+    presses: Vec<isize>,
+    best: Option<(isize, Vec<isize>)>,
+}
+
+impl Search<'_> {
+    fn branch(&mut self, index: usize, remaining: (isize, isize), cost: isize) {
+        if self
+            .best
+            .as_ref()
+            .is_some_and(|(best, _)| cost + self.lower_bound(index, remaining) >= *best)
+        {
+            return;
+        }
+        if index + 2 == self.buttons.len() {
+            let (a, b) = (&self.buttons[index], &self.buttons[index + 1]);
+            if let Some((presses_a, presses_b)) = pair_steps(a, b, remaining) {
+                let cost = cost + presses_a * a.cost + presses_b * b.cost;
+                if self.best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                    self.presses[index] = presses_a;
+                    self.presses[index + 1] = presses_b;
+                    self.best = Some((cost, self.presses.clone()));
+                }
+            }
+            return;
+        }
+        let button = self.buttons[index];
+        for presses in 0..=button.max_presses(remaining) {
+            self.presses[index] = presses;
+            self.branch(
+                index + 1,
+                (
+                    remaining.0 - presses * button.vec.0,
+                    remaining.1 - presses * button.vec.1,
+                ),
+                cost + presses * button.cost,
+            );
+        }
+        self.presses[index] = 0;
+    }
+
+    /// Cost of covering `remaining` on each axis with the buttons from
+    /// `index` on, if every press could use the cheapest cost per step.
+    fn lower_bound(&self, index: usize, remaining: (isize, isize)) -> isize {
+        let axis_bound = |remaining: isize, component: fn(&Button) -> isize| {
+            self.buttons[index..]
+                .iter()
+                .filter(|button| component(button) > 0)
+                .map(|button| {
+                    (remaining as i128 * button.cost as i128).div_euclid(component(button) as i128)
+                        as isize
+                })
+                .min()
+                .unwrap_or(0)
+        };
+        axis_bound(remaining.0, |button| button.vec.0)
+            .max(axis_bound(remaining.1, |button| button.vec.1))
     }
 }
 
 impl FromStr for Machine {
-    type Err = ();
+    type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines();
-        let button_re = Regex::new(r"Button [AB]: X\+(?<x>\d+), Y\+(?<y>\d+)").unwrap();
-        let button_a = button_re.captures(lines.next().unwrap()).unwrap();
-        let button_b = button_re.captures(lines.next().unwrap()).unwrap();
+        let button_re = Regex::new(
+            r"Button (?<name>\w+): X\+(?<x>\d+), Y\+(?<y>\d+)(?:, Cost=(?<cost>\d+))?(?:, Limit=(?<limit>\d+))?",
+        )
+        .unwrap();
         let price_re = Regex::new(r"Prize: X=(?<x>\d+), Y=(?<y>\d+)").unwrap();
-        let price = price_re.captures(lines.next().unwrap()).unwrap();
+        let mut buttons = Vec::new();
+        let mut price_location = None;
+        for line in s.lines() {
+            if let Some(button) = button_re.captures(line) {
+                let cost = match (button.name("cost"), &button["name"]) {
+                    (Some(cost), _) => cost.as_str().parse().unwrap(),
+                    (None, "A") => COST_A,
+                    (None, "B") => COST_B,
+                    (None, name) => return Err(format!("Button {name} has no cost")),
+                };
+                buttons.push(Button {
+                    vec: (button["x"].parse().unwrap(), button["y"].parse().unwrap()),
+                    cost,
+                    limit: button.name("limit").map(|l| l.as_str().parse().unwrap()),
+                });
+            } else if let Some(price) = price_re.captures(line) {
+                price_location = Some((price["x"].parse().unwrap(), price["y"].parse().unwrap()));
+            } else {
+                return Err(format!("Invalid line: {line}"));
+            }
+        }
         Ok(Machine {
-            button_a_vec: (
-                button_a["x"].parse().unwrap(),
-                button_a["y"].parse().unwrap(),
-            ),
-            button_b_vec: (
-                button_b["x"].parse().unwrap(),
-                button_b["y"].parse().unwrap(),
-            ),
-            price_location: (price["x"].parse().unwrap(), price["y"].parse().unwrap()),
+            buttons,
+            price_location: price_location.ok_or("No prize found")?,
         })
     }
 }
@@ -77,8 +245,8 @@ impl Solution for Day {
         let machines = parse_machines(input);
         Some(
             machines
-                .iter()
-                .map(Machine::min_tokens_price)
+                .into_iter()
+                .filter_map(|machine| machine.with_default_limit(100).min_tokens_price())
                 .sum::<usize>()
                 .into(),
         )
@@ -88,15 +256,15 @@ impl Solution for Day {
         let machines = parse_machines(input);
         Some(
             machines
-                .iter()
+                .into_iter()
                 .map(|machine| Machine {
                     price_location: (
-                        machine.price_location.0 + 10_000_000_000_000.0,
-                        machine.price_location.1 + 10_000_000_000_000.0,
+                        machine.price_location.0 + 10_000_000_000_000,
+                        machine.price_location.1 + 10_000_000_000_000,
                     ),
-                    ..*machine
+                    ..machine
                 })
-                .map(|machine| machine.min_tokens_price())
+                .filter_map(|machine| machine.min_tokens_price())
                 .sum::<usize>()
                 .into(),
         )
@@ -132,4 +300,56 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(73_267_584_326_867.into()));
     }
+
+    #[test]
+    fn test_collinear_buttons() {
+        let machine: Machine = "Button A: X+2, Y+4\nButton B: X+1, Y+2\nPrize: X=10, Y=20"
+            .parse()
+            .unwrap();
+        assert_eq!(machine.steps_to_price(), Some(vec![0, 10]));
+        let machine: Machine = "Button A: X+3, Y+6\nButton B: X+2, Y+4\nPrize: X=6, Y=12"
+            .parse()
+            .unwrap();
+        assert_eq!(machine.steps_to_price(), Some(vec![0, 3]));
+        let machine: Machine = "Button A: X+2, Y+4\nButton B: X+1, Y+2\nPrize: X=10, Y=21"
+            .parse()
+            .unwrap();
+        assert_eq!(machine.steps_to_price(), None);
+    }
+
+    #[test]
+    fn test_press_limit() {
+        let machine: Machine = "Button A: X+1, Y+1\nButton B: X+2, Y+1\nPrize: X=300, Y=200"
+            .parse()
+            .unwrap();
+        assert_eq!(machine.steps_to_price(), Some(vec![100, 100]));
+        assert_eq!(
+            machine.clone().with_default_limit(99).steps_to_price(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_many_buttons() {
+        let machine: Machine = "Button A: X+3, Y+1
+Button B: X+1, Y+3
+Button C: X+2, Y+2, Cost=1
+Button D: X+5, Y+0, Cost=2, Limit=1
+Prize: X=14, Y=10"
+            .parse()
+            .unwrap();
+        let presses = machine.steps_to_price().unwrap();
+        assert_eq!(presses, vec![2, 0, 4, 0]);
+        assert_eq!(machine.min_tokens_price(), Some(10));
+        assert_eq!(
+            presses
+                .iter()
+                .zip(&machine.buttons)
+                .fold((0, 0), |(x, y), (p, b)| (x + p * b.vec.0, y + p * b.vec.1)),
+            (14, 10)
+        );
+        assert!("Button C: X+1, Y+1\nPrize: X=1, Y=1"
+            .parse::<Machine>()
+            .is_err());
+    }
 }
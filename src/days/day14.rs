@@ -3,6 +3,7 @@
 use std::str::FromStr;
 
 use crate::solution::{Solution, SolvedValue};
+use crate::utils::{cycle, math};
 use rayon::prelude::*;
 
 fn parse_input(input: &str) -> ((isize, isize), Vec<Robot>) {
@@ -29,8 +30,8 @@ struct Robot {
 
 impl Robot {
     fn step(&mut self, count: isize, (width, height): (isize, isize)) {
-        self.x = (self.x + self.dx * count + width * count) % width;
-        self.y = (self.y + self.dy * count + height * count) % height;
+        self.x = math::wrap(self.x + math::mul_mod(self.dx, count, width), width);
+        self.y = math::wrap(self.y + math::mul_mod(self.dy, count, height), height);
     }
 
     fn quadrant(&self, (width, height): (isize, isize)) -> usize {
@@ -109,6 +110,19 @@ fn has_robots_block(robots: &[Robot], (width, height): (isize, isize), size: usi
     false
 }
 
+/// Steps until every `(position, velocity)` pair on an axis of `length`
+/// tiles is back where it was.
+fn axis_period(robots: impl Iterator<Item = (isize, isize)>, length: isize) -> isize {
+    let positions: Vec<(isize, isize)> = robots.collect();
+    cycle::brent(&positions, |positions| {
+        positions
+            .iter()
+            .map(|&(position, velocity)| (math::wrap(position + velocity, length), velocity))
+            .collect::<Vec<_>>()
+    })
+    .length as isize
+}
+
 pub struct Day;
 
 impl Solution for Day {
@@ -132,7 +146,13 @@ impl Solution for Day {
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
         let (size, robots) = parse_input(input);
-        (1..10_000)
+        // The two axes move independently, so the whole pattern repeats once
+        // both of them do
+        let period = math::lcm(
+            axis_period(robots.iter().map(|robot| (robot.x, robot.dx)), size.0),
+            axis_period(robots.iter().map(|robot| (robot.y, robot.dy)), size.1),
+        );
+        (1..period)
             .into_par_iter()
             .find_any(|&i| {
                 let mut robots_clone = robots.clone();
@@ -170,4 +190,20 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(7_344.into()));
     }
+
+    #[test]
+    fn test_axis_period() {
+        let (size, robots) = parse_input(&read_input(DAY, false, 1).unwrap());
+        assert_eq!(
+            axis_period(robots.iter().map(|robot| (robot.x, robot.dx)), size.0),
+            101
+        );
+        assert_eq!(
+            axis_period(robots.iter().map(|robot| (robot.y, robot.dy)), size.1),
+            103
+        );
+        // Velocities sharing a factor with the width repeat sooner
+        assert_eq!(axis_period([(0, 2), (3, 4)].into_iter(), 6), 3);
+        assert_eq!(axis_period([(1, 0)].into_iter(), 6), 1);
+    }
 }
@@ -9,6 +9,12 @@ use std::{
 
 use crate::solution::{Solution, SolvedValue};
 
+#[cfg(test)]
+mod replay;
+
+/// Where every moved tile ends up, the robot included.
+type Changes = HashMap<(usize, usize), (usize, usize)>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum State {
     Robot,
@@ -117,7 +123,7 @@ impl FromStr for Warehouse {
 
         for (row, line) in s.lines().enumerate() {
             for (col, c) in line.chars().enumerate() {
-                size = (row, col);
+                size = (row + 1, col + 1);
                 match State::try_from(c)? {
                     State::Robot => {
                         robot = (row, col);
@@ -130,7 +136,7 @@ impl FromStr for Warehouse {
                     }
                     State::Empty => (),
                     State::Box2 => return Err(format!("Invalid character: {c}")),
-                };
+                }
             }
         }
 
@@ -177,25 +183,38 @@ impl Warehouse {
         }
     }
 
-    fn move_robot(&mut self, direction: Direction) {
-        if let Some(changes) = self.can_move_in_dir(self.robot, direction) {
-            let mut new_blocks = HashSet::new();
-            for (before, after) in changes {
-                if self.blocks.contains(&before) {
-                    self.blocks.remove(&before);
-                    new_blocks.insert(after);
-                }
+    /// Moves the robot if nothing blocks it and returns what moved.
+    fn move_robot(&mut self, direction: Direction) -> Option<Changes> {
+        let changes = self.can_move_in_dir(self.robot, direction)?;
+        self.apply_changes(&changes, false);
+        Some(changes)
+    }
+
+    /// Moves every box and the robot as recorded in `changes`, or back to
+    /// where they came from if `reverse`.
+    fn apply_changes(&mut self, changes: &Changes, reverse: bool) {
+        let moves = changes.iter().map(|(&before, &after)| {
+            if reverse {
+                (after, before)
+            } else {
+                (before, after)
+            }
+        });
+        let mut new_blocks = HashSet::new();
+        let mut robot = self.robot;
+        for (from, to) in moves {
+            if self.blocks.remove(&from) {
+                new_blocks.insert(to);
+            }
+            if from == self.robot {
+                robot = to;
             }
-            self.blocks.extend(new_blocks);
-            self.robot = self.robot + direction;
         }
+        self.blocks.extend(new_blocks);
+        self.robot = robot;
     }
 
-    fn can_move_in_dir(
-        &self,
-        start_pos: (usize, usize),
-        direction: Direction,
-    ) -> Option<HashMap<(usize, usize), (usize, usize)>> {
+    fn can_move_in_dir(&self, start_pos: (usize, usize), direction: Direction) -> Option<Changes> {
         let new_pos = start_pos + direction;
         if self.get_state(new_pos) == State::Wall {
             return None;
@@ -290,6 +309,7 @@ mod tests {
     use super::*;
     use crate::solution::Solution;
     use crate::utils::read_input;
+    use replay::{InvalidState, Replay};
 
     const DAY: usize = 15;
 
@@ -314,4 +334,308 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(1_533_076.into()));
     }
+
+    #[test]
+    fn test_replay() {
+        let input = read_input(DAY, true, 1).unwrap();
+        let (warehouse, directions) = parse_input(&input);
+        let start = warehouse.grid();
+        assert_eq!(
+            start,
+            input.split_once("\n\n").unwrap().0.to_string() + "\n"
+        );
+        let moves = directions.len();
+        let mut replay = Replay::new(warehouse, directions);
+
+        let end = "\
+##########
+#.O.O.OOO#
+#........#
+#OO......#
+#OO@.....#
+#O#.....O#
+#O.....OO#
+#O.....OO#
+#OO....OO#
+##########
+";
+        assert_eq!(replay.seek(usize::MAX).unwrap().grid(), end);
+        assert_eq!(replay.step(), moves);
+        assert_eq!(replay.warehouse().gps_sum(), 10_092);
+        assert_eq!(replay.forward(), Ok(false));
+
+        let middle = replay.seek(100).unwrap().grid();
+        assert_eq!(replay.step(), 100);
+        assert_eq!(replay.seek(0).unwrap().grid(), start);
+        assert!(!replay.undo());
+        assert_eq!(replay.seek(100).unwrap().grid(), middle);
+        assert!(replay.undo());
+        assert!(replay.redo());
+        // Moves undone by seeking back are still there to redo
+        assert!(replay.redo());
+        assert_eq!(replay.step(), 101);
+        assert_eq!(replay.seek(moves).unwrap().grid(), end);
+    }
+
+    /// The grid after every move of the smaller example, as the puzzle lists
+    /// them.
+    const WALKTHROUGH: &str = "\
+Initial state:
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move <:
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move ^:
+########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move ^:
+########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move >:
+########
+#..@OO.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move >:
+########
+#...@OO#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move >:
+########
+#...@OO#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move v:
+########
+#....OO#
+##..@..#
+#...O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move v:
+########
+#....OO#
+##..@..#
+#...O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move <:
+########
+#....OO#
+##.@...#
+#...O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move v:
+########
+#....OO#
+##.....#
+#..@O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move >:
+########
+#....OO#
+##.....#
+#...@O.#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move >:
+########
+#....OO#
+##.....#
+#....@O#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move v:
+########
+#....OO#
+##.....#
+#.....O#
+#.#.O@.#
+#...O..#
+#...O..#
+########
+
+Move <:
+########
+#....OO#
+##.....#
+#.....O#
+#.#O@..#
+#...O..#
+#...O..#
+########
+
+Move <:
+########
+#....OO#
+##.....#
+#.....O#
+#.#O@..#
+#...O..#
+#...O..#
+########";
+
+    #[test]
+    fn test_replay_walkthrough() {
+        let (warehouse, directions) = parse_input(
+            "\
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+<^^>>>vv<v>>v<<",
+        );
+        let mut replay = Replay::new(warehouse, directions);
+        let grids: Vec<String> = WALKTHROUGH
+            .split("\n\n")
+            .map(|block| block.split_once('\n').unwrap().1.to_string() + "\n")
+            .collect();
+        assert_eq!(grids.len(), 16);
+        assert_eq!(replay.warehouse().grid(), grids[0]);
+        for (step, grid) in grids.iter().enumerate().skip(1) {
+            replay.forward().unwrap();
+            assert_eq!(replay.warehouse().grid(), *grid, "after {step} moves");
+        }
+        assert_eq!(replay.forward(), Ok(false));
+        for (step, grid) in grids.iter().enumerate().rev() {
+            assert_eq!(
+                replay.seek(step).unwrap().grid(),
+                *grid,
+                "seeking to {step}"
+            );
+        }
+        replay.seek(usize::MAX).unwrap();
+        assert_eq!(replay.warehouse().gps_sum(), 2_028);
+    }
+
+    #[test]
+    fn test_replay_widened() {
+        let (mut warehouse, directions) = parse_input(
+            "\
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^",
+        );
+        warehouse.widen();
+        let mut replay = Replay::new(warehouse, directions);
+        assert_eq!(
+            replay.seek(usize::MAX).unwrap().grid(),
+            "\
+##############
+##...[].##..##
+##...@.[]...##
+##....[]....##
+##..........##
+##..........##
+##############
+"
+        );
+        assert_eq!(replay.warehouse().gps_sum(), 105 + 207 + 306);
+        assert_eq!(
+            replay.seek(0).unwrap().grid().lines().nth(3),
+            Some("##....[][]@.##")
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let (mut warehouse, _) = parse_input("#####\n#@O.#\n#####\n\n");
+        assert_eq!(warehouse.validate(), Ok(()));
+        warehouse.robot = (1, 2);
+        assert_eq!(warehouse.validate(), Err(InvalidState::RobotInBox((1, 2))));
+        warehouse.robot = (0, 2);
+        assert_eq!(warehouse.validate(), Err(InvalidState::RobotInWall((0, 2))));
+        warehouse.robot = (1, 1);
+        warehouse.blocks.insert((1, 4));
+        assert_eq!(warehouse.validate(), Err(InvalidState::BoxInWall((1, 4))));
+
+        warehouse.blocks.remove(&(1, 4));
+        warehouse.widen();
+        assert_eq!(warehouse.validate(), Ok(()));
+        warehouse.blocks.insert((1, 5));
+        assert_eq!(
+            warehouse.validate(),
+            Err(InvalidState::OverlappingBoxes((1, 4), (1, 5)))
+        );
+        assert_eq!(
+            InvalidState::OverlappingBoxes((1, 4), (1, 5)).to_string(),
+            "Boxes at (1, 4) and (1, 5) overlap"
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use super::{Changes, Direction, Warehouse};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidState {
+    RobotInWall((usize, usize)),
+    RobotInBox((usize, usize)),
+    BoxInWall((usize, usize)),
+    /// Two boxes, given by their left ends, share a tile.
+    OverlappingBoxes((usize, usize), (usize, usize)),
+}
+
+impl Display for InvalidState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidState::RobotInWall(pos) => write!(f, "Robot at {pos:?} is inside a wall"),
+            InvalidState::RobotInBox(pos) => write!(f, "Robot at {pos:?} is inside a box"),
+            InvalidState::BoxInWall(pos) => write!(f, "Box at {pos:?} is inside a wall"),
+            InvalidState::OverlappingBoxes(a, b) => {
+                write!(f, "Boxes at {a:?} and {b:?} overlap")
+            }
+        }
+    }
+}
+
+impl Warehouse {
+    /// Tiles covered by a box or wall whose left end is at `pos`.
+    fn tiles(&self, (row, col): (usize, usize)) -> Vec<(usize, usize)> {
+        if self.widened {
+            vec![(row, col), (row, col + 1)]
+        } else {
+            vec![(row, col)]
+        }
+    }
+
+    fn is_wall(&self, (row, col): (usize, usize)) -> bool {
+        self.walls.contains(&(row, col))
+            || (self.widened && col > 0 && self.walls.contains(&(row, col - 1)))
+    }
+
+    /// Checks that no two things share a tile.
+    pub fn validate(&self) -> Result<(), InvalidState> {
+        let mut blocks: Vec<(usize, usize)> = self.blocks.iter().copied().collect();
+        blocks.sort_unstable();
+        let mut covered = HashMap::new();
+        for block in blocks {
+            for tile in self.tiles(block) {
+                if self.is_wall(tile) {
+                    return Err(InvalidState::BoxInWall(block));
+                }
+                if let Some(other) = covered.insert(tile, block) {
+                    return Err(InvalidState::OverlappingBoxes(other, block));
+                }
+            }
+        }
+        if self.is_wall(self.robot) {
+            return Err(InvalidState::RobotInWall(self.robot));
+        }
+        if covered.contains_key(&self.robot) {
+            return Err(InvalidState::RobotInBox(self.robot));
+        }
+        Ok(())
+    }
+
+    /// The tiles without the robot position below them.
+    pub fn grid(&self) -> String {
+        let text = self.to_string();
+        let (grid, _) = text.rsplit_once('\n').unwrap();
+        format!("{grid}\n")
+    }
+}
+
+/// Steps a warehouse through its moves one at a time, remembering the
+/// changes of every move so it can be undone and redone.
+pub struct Replay {
+    warehouse: Warehouse,
+    directions: Vec<Direction>,
+    /// Changes of every move taken so far, `None` if the robot was blocked.
+    /// Moves past `step` have been undone.
+    history: Vec<Option<Changes>>,
+    step: usize,
+}
+
+impl Replay {
+    pub fn new(warehouse: Warehouse, directions: Vec<Direction>) -> Self {
+        Replay {
+            warehouse,
+            directions,
+            history: Vec::new(),
+            step: 0,
+        }
+    }
+
+    pub fn warehouse(&self) -> &Warehouse {
+        &self.warehouse
+    }
+
+    /// Number of moves applied.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Applies the next move, `Ok(false)` once all moves are done.
+    pub fn forward(&mut self) -> Result<bool, InvalidState> {
+        if self.redo() {
+            return Ok(true);
+        }
+        let Some(&direction) = self.directions.get(self.step) else {
+            return Ok(false);
+        };
+        let changes = self.warehouse.move_robot(direction);
+        self.history.push(changes);
+        self.step += 1;
+        self.warehouse.validate()?;
+        Ok(true)
+    }
+
+    /// Reverts the last move, `false` at the start.
+    pub fn undo(&mut self) -> bool {
+        if self.step == 0 {
+            return false;
+        }
+        self.step -= 1;
+        if let Some(changes) = &self.history[self.step] {
+            self.warehouse.apply_changes(changes, true);
+        }
+        true
+    }
+
+    /// Applies the last undone move again, `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(changes) = self.history.get(self.step) else {
+            return false;
+        };
+        if let Some(changes) = changes {
+            self.warehouse.apply_changes(changes, false);
+        }
+        self.step += 1;
+        true
+    }
+
+    /// Moves back or forward until `step` moves are applied, or all of them
+    /// if there are fewer.
+    pub fn seek(&mut self, step: usize) -> Result<&Warehouse, InvalidState> {
+        while self.step > step {
+            self.undo();
+        }
+        while self.step < step && self.forward()? {}
+        Ok(&self.warehouse)
+    }
+}
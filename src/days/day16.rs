@@ -9,10 +9,13 @@ use std::{
 
 use crate::solution::{Solution, SolvedValue};
 
+#[cfg(test)]
+mod routes;
+
 type Position = (usize, usize);
 type DirPos = (Position, Direction);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Direction {
     North,
     East,
@@ -111,34 +114,114 @@ impl FromStr for Maze {
     }
 }
 
-impl Display for Maze {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+/// Cost of a step forward and of a quarter turn, which is always followed by
+/// a step. `step` must be positive for the best paths to be well defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Costs {
+    step: usize,
+    turn: usize,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Costs {
+            step: 1,
+            turn: 1000,
+        }
+    }
+}
+
+/// Outcome of a single search from the start facing east.
+struct BestPaths {
+    cost: usize,
+    /// Lowest known cost of every reached state and all states it is
+    /// reached from at that cost.
+    states: HashMap<DirPos, (usize, Vec<DirPos>)>,
+    /// States on the end tile reached at `cost`.
+    ends: Vec<DirPos>,
+}
+
+impl BestPaths {
+    /// Every tile on at least one best path.
+    fn tiles(&self) -> HashSet<Position> {
+        let mut visited = HashSet::new();
+        let mut candidates = self.ends.clone();
+        while let Some(state) = candidates.pop() {
+            if visited.insert(state) {
+                candidates.extend(self.states[&state].1.iter().copied());
+            }
+        }
+        visited.into_iter().map(|(pos, _)| pos).collect()
+    }
+
+    /// The heading of every step of one best path.
+    #[cfg(test)]
+    fn path(&self) -> Vec<Direction> {
+        let mut path = Vec::new();
+        let mut state = self.ends[0];
+        // Every predecessor is strictly cheaper, so this reaches the start
+        while let Some(&previous) = self.states[&state].1.first() {
+            path.push(state.1);
+            state = previous;
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl Maze {
+    fn tile(&self, (y, x): Position) -> char {
+        if (y, x) == self.start {
+            'S'
+        } else if (y, x) == self.end {
+            'E'
+        } else if self.walls[y][x] {
+            '#'
+        } else {
+            '.'
+        }
+    }
+
+    /// The maze with `O` on every tile of `marked`, start and end included.
+    fn render(&self, marked: &HashSet<Position>) -> String {
+        let mut out = String::new();
         for (y, row) in self.walls.iter().enumerate() {
-            for (x, &wall) in row.iter().enumerate() {
-                if (x, y) == self.start {
-                    write!(f, "S")?;
-                } else if (x, y) == self.end {
-                    write!(f, "E")?;
-                } else if wall {
-                    write!(f, "#")?;
+            for x in 0..row.len() {
+                if marked.contains(&(y, x)) {
+                    out.push('O');
                 } else {
-                    write!(f, ".")?;
+                    out.push(self.tile((y, x)));
                 }
             }
-            writeln!(f)?;
+            out.push('\n');
         }
-        Ok(())
+        out
     }
-}
 
-impl Maze {
-    fn dijkstra_to_end(
+    /// States reachable from `(pos, dir)` in one move, with the cost of the move.
+    fn moves(
         &self,
-    ) -> HashMap<DirPos, (usize, Vec<DirPos>)> {
-        let mut costs: HashMap<
-            DirPos,
-            (usize, Vec<DirPos>),
-        > = HashMap::new();
+        (pos, dir): DirPos,
+        costs: Costs,
+    ) -> impl Iterator<Item = (DirPos, usize)> + '_ {
+        [
+            (dir, costs.step),
+            (dir.turn_left(), costs.turn + costs.step),
+            (dir.turn_right(), costs.turn + costs.step),
+        ]
+        .into_iter()
+        .map(move |(dir, cost)| ((pos + dir, dir), cost))
+        .filter(|&(((y, x), _), _)| !self.walls[y][x])
+    }
+
+    /// Dijkstra over `(position, direction)` states, keeping every cheapest
+    /// predecessor so all best paths can be recovered from one run.
+    fn best_paths(&self, costs: Costs) -> Option<BestPaths> {
+        let start = (self.start, Direction::East);
+        let mut states: HashMap<DirPos, (usize, Vec<DirPos>)> =
+            HashMap::from([(start, (0, vec![]))]);
+        let mut best = None;
+        let mut ends = Vec::new();
 
         let mut candidates = BinaryHeap::new();
         candidates.push(DijkstraEntry {
@@ -148,108 +231,46 @@ impl Maze {
         });
 
         while let Some(entry) = candidates.pop() {
+            let state = (entry.pos, entry.dir);
+            if entry.cost > states[&state].0 {
+                continue;
+            }
+            if best.is_some_and(|best| entry.cost > best) {
+                break;
+            }
             if entry.pos == self.end {
+                best = Some(entry.cost);
+                ends.push(state);
                 continue;
             }
-            let (base_cost, _) = *costs
-                .get(&(entry.pos, entry.dir))
-                .unwrap_or(&(0usize, vec![]));
-            let all_dirs = [
-                (entry.dir, 1),
-                (entry.dir.turn_left(), 1001),
-                (entry.dir.turn_right(), 1001),
-            ];
-            let valid_dirs = all_dirs.iter().filter(|&&(dir, _)| {
-                let next_pos = entry.pos + dir;
-                !self.walls[next_pos.0][next_pos.1]
-            });
-            for (dir, cost) in valid_dirs {
-                let next_pos = entry.pos + *dir;
-                let next_cost = base_cost + cost;
-                if let Some(&(prev_cost, _)) = costs.get(&(next_pos, *dir)) {
-                    match next_cost.cmp(&prev_cost) {
-                        std::cmp::Ordering::Less => {
-                            costs.insert(
-                                (next_pos, *dir),
-                                (next_cost, vec![(entry.pos, entry.dir)]),
-                            );
-                            candidates.push(DijkstraEntry {
-                                pos: next_pos,
-                                dir: *dir,
-                                cost: next_cost,
-                            });
-                        }
-                        std::cmp::Ordering::Equal => {
-                            costs
-                                .get_mut(&(next_pos, *dir))
-                                .unwrap()
-                                .1
-                                .push((entry.pos, entry.dir));
-                        }
-                        std::cmp::Ordering::Greater => {}
+            for (next, cost) in self.moves(state, costs) {
+                let next_cost = entry.cost + cost;
+                match states.get_mut(&next) {
+                    Some((prev_cost, _)) if next_cost > *prev_cost => {}
+                    Some((prev_cost, previous)) if next_cost == *prev_cost => previous.push(state),
+                    _ => {
+                        states.insert(next, (next_cost, vec![state]));
+                        candidates.push(DijkstraEntry {
+                            pos: next.0,
+                            dir: next.1,
+                            cost: next_cost,
+                        });
                     }
-                } else {
-                    costs.insert((next_pos, *dir), (next_cost, vec![(entry.pos, entry.dir)]));
-                    candidates.push(DijkstraEntry {
-                        pos: next_pos,
-                        dir: *dir,
-                        cost: next_cost,
-                    });
                 }
             }
         }
 
-        costs
-    }
-
-    fn min_cost(&self) -> usize {
-        let costs = self.dijkstra_to_end();
-        [
-            costs.get(&(self.end, Direction::North)),
-            costs.get(&(self.end, Direction::East)),
-            costs.get(&(self.end, Direction::South)),
-            costs.get(&(self.end, Direction::West)),
-        ]
-        .iter()
-        .filter_map(|x| x.map(|x| x.0))
-        .min()
-        .unwrap()
+        Some(BestPaths {
+            cost: best?,
+            states,
+            ends,
+        })
     }
+}
 
-    fn pos_on_best_paths(&self) -> usize {
-        let min_cost = self.min_cost();
-        let costs = self.dijkstra_to_end();
-        let mut visited = HashSet::new();
-        let mut candidates: Vec<DirPos> = vec![
-            (self.end, Direction::North),
-            (self.end, Direction::East),
-            (self.end, Direction::South),
-            (self.end, Direction::West),
-        ]
-        .into_iter()
-        .filter(|end| {
-            if let Some(x) = costs.get(end) {
-                x.0 == min_cost
-            } else {
-                false
-            }
-        })
-        .collect();
-        while let Some(pos) = candidates.pop() {
-            if !visited.contains(&pos) {
-                visited.insert(pos);
-                if let Some((_, prev_pos)) = costs.get(&pos) {
-                    for &prev_pos in prev_pos {
-                        candidates.push(prev_pos);
-                    }
-                }
-            }
-        }
-        visited
-            .iter()
-            .map(|(pos, _)| *pos)
-            .collect::<HashSet<Position>>()
-            .len()
+impl Display for Maze {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(&HashSet::new()))
     }
 }
 
@@ -258,14 +279,14 @@ pub struct Day;
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
         let maze: Maze = input.parse().unwrap();
-        let cost = maze.min_cost();
-        Some(cost.into())
+        let best = maze.best_paths(Costs::default())?;
+        Some(best.cost.into())
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
         let maze = input.parse::<Maze>().unwrap();
-        let count = maze.pos_on_best_paths();
-        Some(count.into())
+        let best = maze.best_paths(Costs::default())?;
+        Some(best.tiles().len().into())
     }
 }
 
@@ -299,6 +320,60 @@ mod tests {
         assert_eq!(Day.part2(&input), Some(513.into()));
     }
 
+    fn follow(maze: &Maze, path: &[Direction], costs: Costs) -> (Position, usize) {
+        let mut state = (maze.start, Direction::East);
+        let mut total = 0;
+        for &dir in path {
+            let (next, cost) = maze
+                .moves(state, costs)
+                .find(|&((_, next_dir), _)| next_dir == dir)
+                .expect("Path walks into a wall or turns around");
+            state = next;
+            total += cost;
+        }
+        (state.0, total)
+    }
+
+    #[test]
+    fn test_best_paths() {
+        let input = read_input(DAY, true, 2).unwrap();
+        let maze: Maze = input.parse().unwrap();
+        assert_eq!(maze.to_string(), input.trim_end().to_string() + "\n");
+
+        let best = maze.best_paths(Costs::default()).unwrap();
+        assert_eq!(best.cost, 7_036);
+        let path = best.path();
+        assert_eq!(path.len(), 36);
+        assert_eq!(follow(&maze, &path, Costs::default()), (maze.end, 7_036));
+        assert_eq!(
+            maze.render(&best.tiles()),
+            "\
+###############
+#.......#....O#
+#.#.###.#.###O#
+#.....#.#...#O#
+#.###.#####.#O#
+#.#.#.......#O#
+#.#.#####.###O#
+#..OOOOOOOOO#O#
+###O#O#####O#O#
+#OOO#O....#O#O#
+#O#O#O###.#O#O#
+#OOOOO#...#O#O#
+#O###.#.#.#O#O#
+#O..#.....#OOO#
+###############
+"
+        );
+
+        // Without turn costs the best paths are the shortest walks
+        let costs = Costs { step: 1, turn: 0 };
+        let best = maze.best_paths(costs).unwrap();
+        assert_eq!(best.cost, 28);
+        assert_eq!(follow(&maze, &best.path(), costs), (maze.end, 28));
+        assert_eq!(best.tiles().len(), 37);
+    }
+
     #[test]
     fn direction_adds() {
         assert_eq!((5, 5) + Direction::North, (4, 5));
@@ -306,4 +381,46 @@ mod tests {
         assert_eq!((5, 5) + Direction::South, (6, 5));
         assert_eq!((5, 5) + Direction::West, (5, 4));
     }
+
+    #[test]
+    fn test_best_routes() {
+        let input = read_input(DAY, true, 1).unwrap();
+        let maze: Maze = input.parse().unwrap();
+        let costs = Costs::default();
+        let routes = maze.best_routes(6, costs);
+        assert_eq!(routes.len(), 6);
+        let route_costs: Vec<usize> = routes.iter().map(|route| route.cost).collect();
+        assert_eq!(&route_costs[..3], [7_036; 3]);
+        assert!(route_costs[3] > 7_036);
+        assert!(route_costs.is_sorted());
+
+        for (index, route) in routes.iter().enumerate() {
+            assert_eq!(route.states[0], (maze.start, Direction::East));
+            assert_eq!(route.states.last().unwrap().0, maze.end);
+            assert_eq!(
+                *maze.running_costs(&route.states, costs).last().unwrap(),
+                route.cost
+            );
+            assert!(routes[..index]
+                .iter()
+                .all(|other| other.states != route.states));
+        }
+
+        let tiles: HashSet<Position> = routes[..3].iter().flat_map(routes::Route::tiles).collect();
+        assert_eq!(tiles, maze.best_paths(costs).unwrap().tiles());
+
+        // Asking for more routes than exist stops at the last one
+        let maze: Maze = "#####\n#S.E#\n#####".parse().unwrap();
+        assert_eq!(
+            maze.best_routes(3, costs),
+            vec![routes::Route {
+                states: vec![
+                    ((1, 1), Direction::East),
+                    ((1, 2), Direction::East),
+                    ((1, 3), Direction::East)
+                ],
+                cost: 2,
+            }]
+        );
+    }
 }
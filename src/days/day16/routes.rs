@@ -0,0 +1,124 @@
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+
+use super::{Costs, DijkstraEntry, DirPos, Direction, Maze, Position};
+
+/// A route from the start facing east to the end, one state per tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub states: Vec<DirPos>,
+    pub cost: usize,
+}
+
+impl Route {
+    pub fn tiles(&self) -> HashSet<Position> {
+        self.states.iter().map(|&(pos, _)| pos).collect()
+    }
+}
+
+impl Maze {
+    /// Cheapest route from `from` to the end that avoids the `banned` states
+    /// and the `banned_moves` between two states.
+    fn shortest_route(
+        &self,
+        from: DirPos,
+        costs: Costs,
+        banned: &HashSet<DirPos>,
+        banned_moves: &HashSet<(DirPos, DirPos)>,
+    ) -> Option<Route> {
+        let mut best: HashMap<DirPos, (usize, Option<DirPos>)> = HashMap::from([(from, (0, None))]);
+        let mut candidates = BinaryHeap::from([DijkstraEntry {
+            pos: from.0,
+            dir: from.1,
+            cost: 0,
+        }]);
+        while let Some(entry) = candidates.pop() {
+            let state = (entry.pos, entry.dir);
+            if entry.cost > best[&state].0 {
+                continue;
+            }
+            if entry.pos == self.end {
+                let mut states = vec![state];
+                while let Some(previous) = best[states.last().unwrap()].1 {
+                    states.push(previous);
+                }
+                states.reverse();
+                return Some(Route {
+                    states,
+                    cost: entry.cost,
+                });
+            }
+            for (next, cost) in self.moves(state, costs) {
+                if banned.contains(&next) || banned_moves.contains(&(state, next)) {
+                    continue;
+                }
+                let next_cost = entry.cost + cost;
+                if best.get(&next).is_none_or(|&(known, _)| next_cost < known) {
+                    best.insert(next, (next_cost, Some(state)));
+                    candidates.push(DijkstraEntry {
+                        pos: next.0,
+                        dir: next.1,
+                        cost: next_cost,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Cost of reaching every state of `states` from the first one.
+    pub fn running_costs(&self, states: &[DirPos], costs: Costs) -> Vec<usize> {
+        let mut total = 0;
+        let mut running = vec![0];
+        for pair in states.windows(2) {
+            total += self
+                .moves(pair[0], costs)
+                .find(|&(next, _)| next == pair[1])
+                .expect("Consecutive states of a route are one move apart")
+                .1;
+            running.push(total);
+        }
+        running
+    }
+
+    /// The `k` cheapest distinct routes, cheapest first, by Yen's algorithm
+    /// over the `(position, direction)` states.
+    ///
+    /// Every route after the first leaves an earlier one at some spur state
+    /// and continues on the cheapest route that neither reuses the shared
+    /// prefix nor any move an earlier route with that prefix took next.
+    pub fn best_routes(&self, k: usize, costs: Costs) -> Vec<Route> {
+        let start = (self.start, Direction::East);
+        let Some(first) = self.shortest_route(start, costs, &HashSet::new(), &HashSet::new())
+        else {
+            return Vec::new();
+        };
+        let mut routes = vec![first];
+        // Ordered by cost, then by states to break ties deterministically
+        let mut candidates: BTreeSet<(usize, Vec<DirPos>)> = BTreeSet::new();
+        while routes.len() < k {
+            let previous = &routes[routes.len() - 1];
+            let running = self.running_costs(&previous.states, costs);
+            for spur in 0..previous.states.len() - 1 {
+                let root = &previous.states[..=spur];
+                let banned_moves = routes
+                    .iter()
+                    .filter(|route| route.states.len() > spur + 1 && route.states[..=spur] == *root)
+                    .map(|route| (route.states[spur], route.states[spur + 1]))
+                    .collect();
+                let banned = root[..spur].iter().copied().collect();
+                let Some(tail) = self.shortest_route(root[spur], costs, &banned, &banned_moves)
+                else {
+                    continue;
+                };
+                let mut states = root[..spur].to_vec();
+                states.extend(tail.states);
+                candidates.insert((running[spur] + tail.cost, states));
+            }
+            let Some((cost, states)) = candidates.pop_first() else {
+                break;
+            };
+            routes.push(Route { states, cost });
+        }
+        routes
+    }
+}
@@ -5,132 +5,31 @@ use std::str::FromStr;
 
 use crate::solution::{Solution, SolvedValue};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct ComboOperand(isize);
-
-impl ComboOperand {
-    fn value(self, registers: [isize; 3]) -> isize {
-        match self.0 {
-            0..=3 => self.0,
-            4 => registers[0],
-            5 => registers[1],
-            6 => registers[2],
-            _ => unreachable!(),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Operation {
-    Adv(ComboOperand),
-    Bxl(isize),
-    Bst(ComboOperand),
-    Jnz(isize),
-    Bxc,
-    Out(ComboOperand),
-    Bdv(ComboOperand),
-    Cdv(ComboOperand),
-}
-
-impl Operation {
-    fn new(instruction: isize, operand: isize) -> Self {
-        match instruction {
-            0 => Operation::Adv(ComboOperand(operand)),
-            1 => Operation::Bxl(operand),
-            2 => Operation::Bst(ComboOperand(operand)),
-            3 => Operation::Jnz(operand),
-            4 => Operation::Bxc,
-            5 => Operation::Out(ComboOperand(operand)),
-            6 => Operation::Bdv(ComboOperand(operand)),
-            7 => Operation::Cdv(ComboOperand(operand)),
-            _ => unreachable!(),
-        }
-    }
+#[cfg(test)]
+mod asm;
+mod compiled;
+mod quine;
+mod vm;
 
-    fn execute(self, pc: usize, registers: [isize; 3]) -> (usize, [isize; 3], Option<isize>) {
-        match self {
-            Self::Adv(operand) => (
-                pc + 1,
-                [
-                    registers[0] / 2isize.pow(operand.value(registers) as u32),
-                    registers[1],
-                    registers[2],
-                ],
-                None,
-            ),
-            Self::Bxl(operand) => (
-                pc + 1,
-                [registers[0], registers[1] ^ operand, registers[2]],
-                None,
-            ),
-            Self::Bst(operand) => (
-                pc + 1,
-                [registers[0], operand.value(registers) & 0b111, registers[2]],
-                None,
-            ),
-            Self::Jnz(operand) => {
-                if registers[0] != 0 {
-                    (operand as usize, registers, None)
-                } else {
-                    (pc + 1, registers, None)
-                }
-            }
-            Self::Bxc => (
-                pc + 1,
-                [registers[0], registers[1] ^ registers[2], registers[2]],
-                None,
-            ),
-            Self::Out(operand) => (pc + 1, registers, Some(operand.value(registers) & 0b111)),
-            Self::Bdv(operand) => (
-                pc + 1,
-                [
-                    registers[0],
-                    registers[0] / 2isize.pow(operand.value(registers) as u32),
-                    registers[2],
-                ],
-                None,
-            ),
-            Self::Cdv(operand) => (
-                pc + 1,
-                [
-                    registers[0],
-                    registers[1],
-                    registers[0] / 2isize.pow(operand.value(registers) as u32),
-                ],
-                None,
-            ),
-        }
-    }
-}
+use vm::{Operation, Vm};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct System {
     registers: [isize; 3],
     program: Vec<Operation>,
-    output: Vec<isize>,
     program_string: String,
 }
 
 impl System {
-    fn execute_program(&mut self) {
-        let mut pc = 0;
-        while pc < self.program.len() {
-            let operation = self.program[pc];
-            let (new_pc, new_registers, output) = operation.execute(pc, self.registers);
-            pc = new_pc;
-            self.registers = new_registers;
-            if let Some(output) = output {
-                self.output.push(output);
-            }
-        }
+    fn vm(&self) -> Vm {
+        Vm::new(self.registers, self.program.clone())
     }
 
-    fn get_output_string(&self) -> String {
-        self.output
-            .iter()
-            .map(isize::to_string)
-            .collect::<Vec<String>>()
-            .join(",")
+    fn program_numbers(&self) -> Vec<isize> {
+        self.program_string
+            .split(',')
+            .map(|n| n.parse().unwrap())
+            .collect()
     }
 }
 
@@ -152,14 +51,13 @@ impl FromStr for System {
             .collect();
         let program = numbers
             .chunks(2)
-            .map(|chunk| Operation::new(chunk[0], chunk[1]))
-            .collect();
+            .map(|chunk| Operation::new(chunk[0], chunk[1]).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?;
 
         Ok(System {
             registers: [registers[0], registers[1], registers[2]],
             program,
             program_string: program_string.to_string(),
-            output: Vec::new(),
         })
     }
 }
@@ -168,71 +66,17 @@ pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let mut system: System = input.parse().unwrap();
-        system.execute_program();
-        Some(system.get_output_string().into())
+        let system: System = input.parse().unwrap();
+        let mut vm = system.vm();
+        vm.run().ok()?;
+        Some(vm.output_string().into())
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
         let system: System = input.parse().unwrap();
-
-        let mut min = 0;
-        let mut max = 0;
-        let mut curr = 1;
-
-        while min == 0 {
-            curr *= 2;
-            let mut system_clone = system.clone();
-            system_clone.registers[0] = curr;
-            system_clone.execute_program();
-            if system_clone.get_output_string().len() == system.program_string.len() {
-                min = curr;
-            }
-        }
-        while max == 0 {
-            curr *= 2;
-            let mut system_clone = system.clone();
-            system_clone.registers[0] = curr;
-            system_clone.execute_program();
-            if system_clone.get_output_string().len() != system.program_string.len() {
-                max = curr;
-            }
-        }
-
-        let mut cur = min;
-        let mut offset_size = 1;
-        let mut max_similar = 0;
-        let mut output = String::new();
-        while cur <= max && max_similar < 31 {
-            cur += offset_size;
-
-            let mut system_clone = system.clone();
-            system_clone.registers[0] = cur;
-            system_clone.execute_program();
-            output = system_clone.get_output_string();
-
-            let mut similar_length = 0;
-            for (a, b) in output.chars().zip(system.program_string.chars()) {
-                if a == b {
-                    similar_length += 1;
-                } else {
-                    break;
-                }
-            }
-
-            if similar_length > max_similar {
-                if max_similar > 1 {
-                    // This is probably specific to my input
-                    offset_size <<= 3;
-                }
-                max_similar = similar_length;
-            }
-        }
-        if output == system.program_string {
-            Some((cur as usize).into())
-        } else {
-            None
-        }
+        quine::find_quine(system.registers, &system.program, &system.program_numbers())
+            .ok()
+            .map(|a| (a as usize).into())
     }
 }
 
@@ -241,6 +85,7 @@ mod tests {
     use super::*;
     use crate::solution::Solution;
     use crate::utils::read_input;
+    use vm::VmError;
 
     const DAY: usize = 17;
 
@@ -255,9 +100,246 @@ mod tests {
         assert_eq!(Day.part1(&input), Some("1,5,3,0,2,5,2,5,3".into()));
     }
 
+    #[test]
+    fn test_part2_example() {
+        let input = read_input(DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input), Some(117_440.into()));
+    }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(108_107_566_389_757.into()));
     }
+
+    #[test]
+    fn test_disassemble() {
+        let system: System = read_input(DAY, false, 1).unwrap().parse().unwrap();
+        assert_eq!(
+            vm::disassemble(&system.program),
+            "  0: bst a  ; B = A & 7
+  2: bxl 3  ; B = B ^ 3
+  4: cdv b  ; C = A >> B
+  6: bxc 1  ; B = B ^ C
+  8: bxl 3  ; B = B ^ 3
+ 10: adv 3  ; A = A >> 3
+ 12: out b  ; out B & 7
+ 14: jnz 0  ; if A != 0 goto 0
+"
+        );
+        let numbers = system.program_numbers();
+        assert_eq!(
+            asm::assemble(&vm::disassemble(&system.program)),
+            Ok(numbers)
+        );
+    }
+
+    #[test]
+    fn test_breakpoints_and_trace() {
+        let system: System = read_input(DAY, true, 1).unwrap().parse().unwrap();
+        let mut vm = system.vm().with_trace();
+        vm.add_breakpoint(4);
+        assert_eq!(vm.run(), Ok(vm::Stop::Breakpoint(4)));
+        assert_eq!(vm.output, vec![4]);
+        assert_eq!(vm.run(), Ok(vm::Stop::Breakpoint(4)));
+        assert_eq!(vm.output, vec![4, 6]);
+        vm.remove_breakpoint(4);
+        assert_eq!(vm.run(), Ok(vm::Stop::Halted));
+        assert_eq!(vm.output_string(), "4,6,3,5,6,3,5,2,1,0");
+
+        let trace = vm.trace.unwrap();
+        assert_eq!(trace.len(), 30);
+        assert_eq!(trace[0].operation, Operation::Adv(vm::ComboOperand(1)));
+        assert_eq!(trace[0].registers, [364, 0, 0]);
+        assert_eq!(trace[1].output, Some(4));
+        assert_eq!(trace.last().unwrap().registers, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut vm = Vm::new([1, 0, 0], vec![Operation::Out(vm::ComboOperand(7))]);
+        assert_eq!(vm.step(), Err(VmError::ReservedOperand { pc: 0 }));
+
+        let mut vm = Vm::new([1, 0, 0], vec![Operation::Jnz(0)]).with_instruction_limit(100);
+        assert_eq!(vm.run(), Err(VmError::InstructionLimit(100)));
+        for (instruction, operand) in [(0, 8), (1, -1), (3, 8), (4, 8), (5, 15)] {
+            assert_eq!(
+                Operation::new(instruction, operand),
+                Err(VmError::InvalidOperand(operand))
+            );
+        }
+        // Combo operand 7 decodes, but can't be executed
+        let mut vm = Vm::new([1, 0, 0], vec![Operation::new(2, 7).unwrap()]);
+        assert_eq!(vm.step(), Err(VmError::ReservedOperand { pc: 0 }));
+
+        assert!(
+            "Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 8,0"
+                .parse::<System>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_quine_errors() {
+        let system: System = read_input(DAY, true, 1).unwrap().parse().unwrap();
+        assert_eq!(
+            quine::find_quine(system.registers, &system.program, &system.program_numbers()),
+            Err(quine::QuineError::NoSolution)
+        );
+        for program in [
+            "0,3,5,4",
+            "0,3,0,3,5,4,3,0",
+            "0,4,5,4,3,0",
+            "1,1,0,3,5,5,3,0",
+        ] {
+            let system: System =
+                format!("Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: {program}")
+                    .parse()
+                    .unwrap();
+            assert!(matches!(
+                quine::find_quine(system.registers, &system.program, &system.program_numbers()),
+                Err(quine::QuineError::UnsupportedShape(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_compiled() {
+        let system: System = read_input(DAY, false, 1).unwrap().parse().unwrap();
+        let program = compiled::Compiled::new(&system.program).unwrap();
+        assert!(program.looped);
+        assert_eq!(program.ops.len(), 7);
+        for a in (0..5_000).chain([system.registers[0], 108_107_566_389_757]) {
+            let mut vm = Vm::new([a, 0, 0], system.program.clone());
+            vm.run().unwrap();
+            assert_eq!(program.run([a, 0, 0]), vm.output);
+        }
+
+        let fused = compiled::Compiled::new(&[
+            Operation::Adv(vm::ComboOperand(1)),
+            Operation::Adv(vm::ComboOperand(2)),
+            Operation::Bxl(5),
+            Operation::Bxl(5),
+            Operation::Out(vm::ComboOperand(4)),
+            Operation::Jnz(0),
+        ])
+        .unwrap();
+        assert_eq!(
+            fused.ops,
+            vec![
+                compiled::Op::Shift {
+                    target: 0,
+                    source: compiled::Source::Literal(3)
+                },
+                compiled::Op::Out(compiled::Source::Register(0)),
+            ]
+        );
+        assert!(compiled::Compiled::new(&[Operation::Jnz(0)]).is_err());
+        assert!(compiled::Compiled::new(&[Operation::Jnz(2), Operation::Bxc(0)]).is_err());
+    }
+
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_compiled_not_slower_than_interpreter() {
+        let system: System = read_input(DAY, false, 1).unwrap().parse().unwrap();
+        let program = compiled::Compiled::new(&system.program).unwrap();
+        let inputs = 1 << 45..(1 << 45) + 200_000;
+
+        let start = std::time::Instant::now();
+        let interpreted: usize = inputs
+            .clone()
+            .map(|a| {
+                let mut vm = Vm::new([a, 0, 0], system.program.clone());
+                vm.run().unwrap();
+                vm.output.len()
+            })
+            .sum();
+        let interpreted_duration = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut output = Vec::with_capacity(16);
+        let compiled: usize = inputs
+            .map(|a| {
+                output.clear();
+                program.run_into([a, 0, 0], &mut output);
+                output.len()
+            })
+            .sum();
+        let compiled_duration = start.elapsed();
+
+        assert_eq!(interpreted, compiled);
+        assert!(
+            compiled_duration <= interpreted_duration,
+            "compiled {compiled_duration:?} is slower than the interpreter {interpreted_duration:?}"
+        );
+    }
+
+    #[test]
+    fn test_assemble() {
+        let numbers = asm::assemble(
+            "# prints the lowest three bits of A until A is zero
+            loop: adv 3 ; A = A >> 3
+                  OUT a
+                  jnz loop",
+        )
+        .unwrap();
+        assert_eq!(asm::program_line(&numbers), "Program: 0,3,5,4,3,0");
+
+        let input = read_input(DAY, true, 2).unwrap();
+        let system: System = input.parse().unwrap();
+        assert_eq!(
+            format!("Program: {}", system.program_string),
+            asm::program_line(&numbers)
+        );
+
+        for source in [
+            "adv 7",
+            "adv d",
+            "bxl 8",
+            "foo 1",
+            "out",
+            "bst a b",
+            "x: bxc\nx: bxc",
+            "2: bxc",
+            "jnz missing",
+        ] {
+            assert!(asm::assemble(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_assemble_disassemble_round_trip() {
+        // Small xorshift generator to get reproducible random programs
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = |max: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % max) as isize
+        };
+        for _ in 0..1_000 {
+            let length = next(8) + 1;
+            let numbers: Vec<isize> = (0..length)
+                .flat_map(|_| {
+                    let opcode = next(8);
+                    let operand = match opcode {
+                        1 | 3 | 4 => next(8),
+                        _ => next(7),
+                    };
+                    [opcode, operand]
+                })
+                .collect();
+            let program: Vec<Operation> = numbers
+                .chunks(2)
+                .map(|chunk| Operation::new(chunk[0], chunk[1]).unwrap())
+                .collect();
+            assert_eq!(
+                program
+                    .iter()
+                    .flat_map(|op| op.encode())
+                    .collect::<Vec<_>>(),
+                numbers
+            );
+            assert_eq!(asm::assemble(&vm::disassemble(&program)), Ok(numbers));
+        }
+    }
 }
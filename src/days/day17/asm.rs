@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+/// Assembles mnemonics into the comma separated numbers of the machine.
+///
+/// Every line holds at most one instruction like `adv a`, `bxl 5` or
+/// `jnz loop`. Combo operands are `0` to `3` or one of the registers `a`, `b`
+/// and `c`. Lines can start with labels (`loop:`) and everything after `;` or
+/// `#` is a comment. A numeric label (`4:`) has to match the address of the
+/// instruction, so the output of [`super::vm::disassemble`] can be assembled
+/// again.
+pub fn assemble(source: &str) -> Result<Vec<isize>, String> {
+    let mut labels = HashMap::new();
+    let mut instructions = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut code = line.split([';', '#']).next().unwrap_or_default().trim();
+        while let Some((label, rest)) = code.split_once(':') {
+            let label = label.trim();
+            let address = instructions.len() * 2;
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != address {
+                    return Err(format!(
+                        "Line {line_number}: address {expected} doesn't match actual address {address}"
+                    ));
+                }
+            } else if is_identifier(label) {
+                if labels.insert(label, address).is_some() {
+                    return Err(format!("Line {line_number}: duplicate label {label}"));
+                }
+            } else {
+                return Err(format!("Line {line_number}: invalid label {label:?}"));
+            }
+            code = rest.trim();
+        }
+        if code.is_empty() {
+            continue;
+        }
+        let mut parts = code.split_whitespace();
+        let mnemonic = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let operand = parts.next();
+        if parts.next().is_some() {
+            return Err(format!("Line {line_number}: too many operands"));
+        }
+        instructions.push((line_number, mnemonic, operand));
+    }
+
+    let mut numbers = Vec::with_capacity(instructions.len() * 2);
+    for (line_number, mnemonic, operand) in instructions {
+        let error = |message: String| format!("Line {line_number}: {message}");
+        let required = || operand.ok_or_else(|| error(format!("{mnemonic} needs an operand")));
+        let (opcode, operand) = match mnemonic.as_str() {
+            "adv" => (0, combo(required()?).map_err(error)?),
+            "bxl" => (1, literal(required()?).map_err(error)?),
+            "bst" => (2, combo(required()?).map_err(error)?),
+            "jnz" => {
+                let target = required()?;
+                let operand = match labels.get(target) {
+                    Some(&address) => isize::try_from(address)
+                        .ok()
+                        .filter(|address| *address <= 7)
+                        .ok_or_else(|| error(format!("label {target} is out of jump range")))?,
+                    None => literal(target).map_err(error)?,
+                };
+                (3, operand)
+            }
+            "bxc" => (4, operand.map_or(Ok(0), literal).map_err(error)?),
+            "out" => (5, combo(required()?).map_err(error)?),
+            "bdv" => (6, combo(required()?).map_err(error)?),
+            "cdv" => (7, combo(required()?).map_err(error)?),
+            _ => return Err(error(format!("unknown mnemonic {mnemonic}"))),
+        };
+        numbers.push(opcode);
+        numbers.push(operand);
+    }
+    Ok(numbers)
+}
+
+/// Formats assembled numbers like the `Program: ...` line of the input.
+pub fn program_line(numbers: &[isize]) -> String {
+    let numbers = numbers
+        .iter()
+        .map(isize::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("Program: {numbers}")
+}
+
+fn is_identifier(label: &str) -> bool {
+    label.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn literal(operand: &str) -> Result<isize, String> {
+    operand
+        .parse()
+        .ok()
+        .filter(|value| (0..=7).contains(value))
+        .ok_or_else(|| format!("invalid literal operand {operand}"))
+}
+
+fn combo(operand: &str) -> Result<isize, String> {
+    match operand.to_ascii_lowercase().as_str() {
+        "a" => Ok(4),
+        "b" => Ok(5),
+        "c" => Ok(6),
+        "7" => Err("combo operand 7 is reserved".to_string()),
+        _ => literal(operand)
+            .ok()
+            .filter(|value| *value <= 3)
+            .ok_or_else(|| format!("invalid combo operand {operand}")),
+    }
+}
@@ -0,0 +1,170 @@
+use super::vm::{dv, ComboOperand, Operation};
+
+/// Where a combo operand takes its value from, resolved at compile time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Literal(isize),
+    Register(usize),
+}
+
+impl TryFrom<ComboOperand> for Source {
+    type Error = String;
+
+    fn try_from(operand: ComboOperand) -> Result<Self, Self::Error> {
+        match operand.0 {
+            literal @ 0..=3 => Ok(Source::Literal(literal)),
+            register @ 4..=6 => Ok(Source::Register(register as usize - 4)),
+            operand => Err(format!("Invalid combo operand {operand}")),
+        }
+    }
+}
+
+/// Optimised operation without jumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `registers[target] = A >> source`
+    Shift { target: usize, source: Source },
+    /// `B = B ^ value`
+    Xor(isize),
+    /// `B = source & 7`
+    Store(Source),
+    /// `B = B ^ C`
+    XorC,
+    /// Outputs `source & 7`
+    Out(Source),
+}
+
+type Instruction = Box<dyn Fn(&mut [isize; 3], &mut Vec<isize>) + Send + Sync>;
+
+/// A program translated into one closure per (optimised) operation.
+///
+/// Only straight-line programs, optionally wrapped in a loop by a final
+/// `jnz 0`, can be compiled. Everything else has to run on the interpreter.
+pub struct Compiled {
+    /// The optimised operations, kept for the tests to inspect.
+    #[cfg(test)]
+    pub ops: Vec<Op>,
+    pub looped: bool,
+    instructions: Vec<Instruction>,
+}
+
+impl Compiled {
+    pub fn new(program: &[Operation]) -> Result<Self, String> {
+        let (body, looped) = match program.split_last() {
+            Some((Operation::Jnz(0), body)) => (body, true),
+            _ => (program, false),
+        };
+        let mut ops: Vec<Op> = Vec::with_capacity(body.len());
+        for operation in body {
+            let op = match *operation {
+                Operation::Adv(operand) => Op::Shift {
+                    target: 0,
+                    source: operand.try_into()?,
+                },
+                Operation::Bdv(operand) => Op::Shift {
+                    target: 1,
+                    source: operand.try_into()?,
+                },
+                Operation::Cdv(operand) => Op::Shift {
+                    target: 2,
+                    source: operand.try_into()?,
+                },
+                Operation::Bxl(operand) => Op::Xor(operand),
+                Operation::Bst(operand) => Op::Store(operand.try_into()?),
+                Operation::Bxc(_) => Op::XorC,
+                Operation::Out(operand) => Op::Out(operand.try_into()?),
+                Operation::Jnz(_) => {
+                    return Err("Only a final jnz 0 can be compiled".to_string());
+                }
+            };
+            match (ops.last_mut(), op) {
+                (
+                    Some(Op::Shift {
+                        target: 0,
+                        source: Source::Literal(previous),
+                    }),
+                    Op::Shift {
+                        target: 0,
+                        source: Source::Literal(shift),
+                    },
+                ) => *previous += shift,
+                (Some(Op::Xor(previous)), Op::Xor(value)) => *previous ^= value,
+                _ => ops.push(op),
+            }
+        }
+        ops.retain(|op| {
+            !matches!(
+                op,
+                Op::Xor(0)
+                    | Op::Shift {
+                        target: 0,
+                        source: Source::Literal(0)
+                    }
+            )
+        });
+        if looped
+            && !ops
+                .iter()
+                .any(|op| matches!(op, Op::Shift { target: 0, .. }))
+        {
+            return Err("The loop never changes A".to_string());
+        }
+        let instructions = ops.iter().map(|op| Self::instruction(*op)).collect();
+        Ok(Self {
+            #[cfg(test)]
+            ops,
+            looped,
+            instructions,
+        })
+    }
+
+    fn instruction(op: Op) -> Instruction {
+        match op {
+            Op::Shift {
+                target,
+                source: Source::Literal(shift),
+            } => Box::new(move |registers, _| registers[target] = dv(registers[0], shift)),
+            Op::Shift {
+                target,
+                source: Source::Register(register),
+            } => Box::new(move |registers, _| {
+                registers[target] = dv(registers[0], registers[register]);
+            }),
+            Op::Xor(value) => Box::new(move |registers, _| registers[1] ^= value),
+            Op::Store(Source::Literal(value)) => {
+                Box::new(move |registers, _| registers[1] = value & 0b111)
+            }
+            Op::Store(Source::Register(register)) => {
+                Box::new(move |registers, _| registers[1] = registers[register] & 0b111)
+            }
+            Op::XorC => Box::new(|registers, _| registers[1] ^= registers[2]),
+            Op::Out(Source::Literal(value)) => {
+                Box::new(move |_, output| output.push(value & 0b111))
+            }
+            Op::Out(Source::Register(register)) => {
+                Box::new(move |registers, output| output.push(registers[register] & 0b111))
+            }
+        }
+    }
+
+    /// Runs the program and appends its output to `output`.
+    ///
+    /// Like the real machine this never returns if the loop doesn't bring A
+    /// down to zero, e.g. when A is shifted by a register holding `0`.
+    pub fn run_into(&self, mut registers: [isize; 3], output: &mut Vec<isize>) -> [isize; 3] {
+        loop {
+            for instruction in &self.instructions {
+                instruction(&mut registers, output);
+            }
+            if !self.looped || registers[0] == 0 {
+                return registers;
+            }
+        }
+    }
+
+    pub fn run(&self, registers: [isize; 3]) -> Vec<isize> {
+        let mut output = Vec::new();
+        self.run_into(registers, &mut output);
+        output
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Display;
+
+use super::compiled::Compiled;
+use super::vm::{ComboOperand, Operation};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuineError {
+    /// The program isn't a single loop that shifts A once and outputs once.
+    UnsupportedShape(String),
+    NoSolution,
+}
+
+impl Display for QuineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuineError::UnsupportedShape(reason) => write!(f, "Unsupported program: {reason}"),
+            QuineError::NoSolution => write!(f, "No value of A makes the program output itself"),
+        }
+    }
+}
+
+/// Registers an operation reads and writes, as `[A, B, C]` flags.
+fn registers_used(operation: Operation) -> ([bool; 3], [bool; 3]) {
+    let combo = |ComboOperand(operand): ComboOperand| [operand == 4, operand == 5, operand == 6];
+    let or = |a: [bool; 3], b: [bool; 3]| [a[0] || b[0], a[1] || b[1], a[2] || b[2]];
+    match operation {
+        Operation::Adv(operand) => (
+            or([true, false, false], combo(operand)),
+            [true, false, false],
+        ),
+        Operation::Bxl(_) => ([false, true, false], [false, true, false]),
+        Operation::Bst(operand) => (combo(operand), [false, true, false]),
+        Operation::Jnz(_) => ([true, false, false], [false; 3]),
+        Operation::Bxc(_) => ([false, true, true], [false, true, false]),
+        Operation::Out(operand) => (combo(operand), [false; 3]),
+        Operation::Bdv(operand) => (
+            or([true, false, false], combo(operand)),
+            [false, true, false],
+        ),
+        Operation::Cdv(operand) => (
+            or([true, false, false], combo(operand)),
+            [false, false, true],
+        ),
+    }
+}
+
+/// Checks that the program is one loop over A which drops the lowest
+/// `shift` bits of A per iteration and prints exactly one value that only
+/// depends on the current A. Returns `shift`.
+fn loop_shift(program: &[Operation]) -> Result<u32, QuineError> {
+    let unsupported = |reason: &str| Err(QuineError::UnsupportedShape(reason.to_string()));
+    let Some((Operation::Jnz(0), body)) = program.split_last().map(|(l, b)| (*l, b)) else {
+        return unsupported("it has to end with jnz 0");
+    };
+    if body.iter().any(|op| matches!(op, Operation::Jnz(_))) {
+        return unsupported("only the final jnz 0 may jump");
+    }
+    let shifts: Vec<_> = body
+        .iter()
+        .filter_map(|op| match op {
+            Operation::Adv(operand) => Some(*operand),
+            _ => None,
+        })
+        .collect();
+    let shift = match shifts.as_slice() {
+        [ComboOperand(shift @ 1..=3)] => *shift as u32,
+        [_] => return unsupported("A has to be shifted by a literal 1, 2 or 3"),
+        _ => return unsupported("A has to be shifted exactly once per loop"),
+    };
+    if body
+        .iter()
+        .filter(|op| matches!(op, Operation::Out(_)))
+        .count()
+        != 1
+    {
+        return unsupported("it has to output exactly once per loop");
+    }
+    let mut written = [true, false, false];
+    for operation in body {
+        let (reads, writes) = registers_used(*operation);
+        if (reads[1] && !written[1]) || (reads[2] && !written[2]) {
+            return unsupported("B and C have to be set from A before they are read");
+        }
+        for register in 0..3 {
+            written[register] |= writes[register];
+        }
+    }
+    Ok(shift)
+}
+
+/// Smallest A for which the program outputs `expected`.
+///
+/// Every loop iteration only sees the bits of A that haven't been shifted
+/// out yet, so the last output only depends on the highest digit (in base
+/// `2^shift`) of A. The search fixes digits from the highest one down and
+/// backtracks when no digit reproduces the wanted suffix of the output.
+pub fn find_quine(
+    registers: [isize; 3],
+    program: &[Operation],
+    expected: &[isize],
+) -> Result<isize, QuineError> {
+    let shift = loop_shift(program)?;
+    let compiled = Compiled::new(program).map_err(QuineError::UnsupportedShape)?;
+    search(registers, &compiled, expected, shift, 0, expected.len()).ok_or(QuineError::NoSolution)
+}
+
+fn search(
+    registers: [isize; 3],
+    program: &Compiled,
+    expected: &[isize],
+    shift: u32,
+    prefix: isize,
+    remaining: usize,
+) -> Option<isize> {
+    if remaining == 0 {
+        return Some(prefix);
+    }
+    (0..1 << shift).find_map(|digit| {
+        let a = (prefix << shift) | digit;
+        if program.run([a, registers[1], registers[2]]) == expected[remaining - 1..] {
+            search(registers, program, expected, shift, a, remaining - 1)
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,349 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmError {
+    /// Combo operand 7 is reserved and must not appear in valid programs.
+    ReservedOperand {
+        pc: usize,
+    },
+    InvalidInstruction(isize),
+    /// Jumps have to land on an instruction, not on an operand.
+    MisalignedJump {
+        pc: usize,
+        target: isize,
+    },
+    /// Operands are 3-bit numbers.
+    InvalidOperand(isize),
+    InstructionLimit(usize),
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmError::ReservedOperand { pc } => write!(f, "Reserved combo operand 7 at {pc}"),
+            VmError::InvalidInstruction(instruction) => {
+                write!(f, "Invalid instruction {instruction}")
+            }
+            VmError::MisalignedJump { pc, target } => {
+                write!(f, "Jump at {pc} to odd address {target}")
+            }
+            VmError::InvalidOperand(operand) => write!(f, "Invalid operand {operand}"),
+            VmError::InstructionLimit(limit) => {
+                write!(f, "Program didn't halt within {limit} instructions")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComboOperand(pub isize);
+
+impl ComboOperand {
+    pub fn value(self, registers: [isize; 3]) -> Option<isize> {
+        match self.0 {
+            0..=3 => Some(self.0),
+            4 => Some(registers[0]),
+            5 => Some(registers[1]),
+            6 => Some(registers[2]),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    fn describe(self) -> &'static str {
+        match self.0 {
+            0 => "0",
+            1 => "1",
+            2 => "2",
+            3 => "3",
+            4 => "A",
+            5 => "B",
+            6 => "C",
+            _ => "<reserved>",
+        }
+    }
+}
+
+impl Display for ComboOperand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            4 => write!(f, "a"),
+            5 => write!(f, "b"),
+            6 => write!(f, "c"),
+            operand => write!(f, "{operand}"),
+        }
+    }
+}
+
+/// `value / 2^shift`, which is `0` once the shift moves every bit out.
+pub(super) fn dv(value: isize, shift: isize) -> isize {
+    u32::try_from(shift)
+        .ok()
+        .and_then(|shift| value.checked_shr(shift))
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Adv(ComboOperand),
+    Bxl(isize),
+    Bst(ComboOperand),
+    Jnz(isize),
+    /// Ignores its operand, which is kept to encode the instruction again.
+    Bxc(u8),
+    Out(ComboOperand),
+    Bdv(ComboOperand),
+    Cdv(ComboOperand),
+}
+
+impl Operation {
+    pub fn new(instruction: isize, operand: isize) -> Result<Self, VmError> {
+        if !(0..=7).contains(&operand) {
+            return Err(VmError::InvalidOperand(operand));
+        }
+        Ok(match instruction {
+            0 => Operation::Adv(ComboOperand(operand)),
+            1 => Operation::Bxl(operand),
+            2 => Operation::Bst(ComboOperand(operand)),
+            3 => Operation::Jnz(operand),
+            4 => Operation::Bxc(u8::try_from(operand).expect("Checked to be 3-bit")),
+            5 => Operation::Out(ComboOperand(operand)),
+            6 => Operation::Bdv(ComboOperand(operand)),
+            7 => Operation::Cdv(ComboOperand(operand)),
+            _ => return Err(VmError::InvalidInstruction(instruction)),
+        })
+    }
+
+    /// Opcode and operand, the inverse of [`Operation::new`].
+    #[cfg(test)]
+    pub fn encode(self) -> [isize; 2] {
+        match self {
+            Self::Adv(ComboOperand(operand)) => [0, operand],
+            Self::Bxl(operand) => [1, operand],
+            Self::Bst(ComboOperand(operand)) => [2, operand],
+            Self::Jnz(operand) => [3, operand],
+            Self::Bxc(operand) => [4, isize::from(operand)],
+            Self::Out(ComboOperand(operand)) => [5, operand],
+            Self::Bdv(ComboOperand(operand)) => [6, operand],
+            Self::Cdv(ComboOperand(operand)) => [7, operand],
+        }
+    }
+
+    /// Runs the operation at address `pc`.
+    ///
+    /// Returns the next pc, the new registers and the output, if any.
+    pub fn execute(
+        self,
+        pc: usize,
+        registers: [isize; 3],
+    ) -> Result<(usize, [isize; 3], Option<isize>), VmError> {
+        let combo = |operand: ComboOperand| {
+            operand
+                .value(registers)
+                .ok_or(VmError::ReservedOperand { pc })
+        };
+        let [a, b, c] = registers;
+        Ok(match self {
+            Self::Adv(operand) => (pc + 2, [dv(a, combo(operand)?), b, c], None),
+            Self::Bxl(operand) => (pc + 2, [a, b ^ operand, c], None),
+            Self::Bst(operand) => (pc + 2, [a, combo(operand)? & 0b111, c], None),
+            Self::Jnz(operand) => {
+                if a == 0 {
+                    (pc + 2, registers, None)
+                } else if operand % 2 == 0 {
+                    (operand as usize, registers, None)
+                } else {
+                    return Err(VmError::MisalignedJump {
+                        pc,
+                        target: operand,
+                    });
+                }
+            }
+            Self::Bxc(_) => (pc + 2, [a, b ^ c, c], None),
+            Self::Out(operand) => (pc + 2, registers, Some(combo(operand)? & 0b111)),
+            Self::Bdv(operand) => (pc + 2, [a, dv(a, combo(operand)?), c], None),
+            Self::Cdv(operand) => (pc + 2, [a, b, dv(a, combo(operand)?)], None),
+        })
+    }
+
+    /// Human readable description of what the operation does.
+    #[cfg(test)]
+    pub fn describe(self) -> String {
+        match self {
+            Self::Adv(operand) => format!("A = A >> {}", operand.describe()),
+            Self::Bxl(operand) => format!("B = B ^ {operand}"),
+            Self::Bst(operand) => format!("B = {} & 7", operand.describe()),
+            Self::Jnz(operand) => format!("if A != 0 goto {operand}"),
+            Self::Bxc(_) => "B = B ^ C".to_string(),
+            Self::Out(operand) => format!("out {} & 7", operand.describe()),
+            Self::Bdv(operand) => format!("B = A >> {}", operand.describe()),
+            Self::Cdv(operand) => format!("C = A >> {}", operand.describe()),
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Adv(operand) => write!(f, "adv {operand}"),
+            Self::Bxl(operand) => write!(f, "bxl {operand}"),
+            Self::Bst(operand) => write!(f, "bst {operand}"),
+            Self::Jnz(operand) => write!(f, "jnz {operand}"),
+            Self::Bxc(0) => write!(f, "bxc"),
+            Self::Bxc(operand) => write!(f, "bxc {operand}"),
+            Self::Out(operand) => write!(f, "out {operand}"),
+            Self::Bdv(operand) => write!(f, "bdv {operand}"),
+            Self::Cdv(operand) => write!(f, "cdv {operand}"),
+        }
+    }
+}
+
+/// One line per operation with its address, mnemonic and decoded meaning.
+#[cfg(test)]
+pub fn disassemble(program: &[Operation]) -> String {
+    use std::fmt::Write;
+
+    let mut listing = String::new();
+    for (index, operation) in program.iter().enumerate() {
+        let mnemonic = operation.to_string();
+        let _ = writeln!(
+            listing,
+            "{:>3}: {mnemonic:<6} ; {}",
+            index * 2,
+            operation.describe()
+        );
+    }
+    listing
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub operation: Operation,
+    pub registers: [isize; 3],
+    pub output: Option<isize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Running,
+    Halted,
+    Breakpoint(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vm {
+    pub registers: [isize; 3],
+    pub program: Vec<Operation>,
+    /// Address of the next instruction, each instruction takes two numbers.
+    pub pc: usize,
+    pub output: Vec<isize>,
+    /// Registers after every executed instruction, if tracing is enabled.
+    pub trace: Option<Vec<TraceEntry>>,
+    breakpoints: HashSet<usize>,
+    instruction_limit: Option<usize>,
+    executed: usize,
+}
+
+impl Vm {
+    pub fn new(registers: [isize; 3], program: Vec<Operation>) -> Self {
+        Self {
+            registers,
+            program,
+            pc: 0,
+            output: Vec::new(),
+            trace: None,
+            breakpoints: HashSet::new(),
+            instruction_limit: None,
+            executed: 0,
+        }
+    }
+
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_instruction_limit(mut self, limit: usize) -> Self {
+        self.instruction_limit = Some(limit);
+        self
+    }
+
+    /// Breakpoints are addresses like the operands of `jnz`, so `0, 2, 4, ...`.
+    #[cfg(test)]
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    #[cfg(test)]
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.pc / 2 >= self.program.len()
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<Stop, VmError> {
+        if self.is_halted() {
+            return Ok(Stop::Halted);
+        }
+        if self
+            .instruction_limit
+            .is_some_and(|limit| self.executed >= limit)
+        {
+            return Err(VmError::InstructionLimit(self.executed));
+        }
+        let operation = self.program[self.pc / 2];
+        let (pc, registers, output) = operation.execute(self.pc, self.registers)?;
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry {
+                pc: self.pc,
+                operation,
+                registers,
+                output,
+            });
+        }
+        self.executed += 1;
+        self.pc = pc;
+        self.registers = registers;
+        if let Some(output) = output {
+            self.output.push(output);
+        }
+        Ok(if self.is_halted() {
+            Stop::Halted
+        } else if self.breakpoints.contains(&self.pc) {
+            Stop::Breakpoint(self.pc)
+        } else {
+            Stop::Running
+        })
+    }
+
+    /// Runs until the program halts or the next breakpoint is reached.
+    ///
+    /// Starting on a breakpoint doesn't stop immediately, so calling `run`
+    /// again continues to the next one.
+    pub fn run(&mut self) -> Result<Stop, VmError> {
+        loop {
+            let stop = self.step()?;
+            if stop != Stop::Running {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// The output joined with commas, like the answer of part 1.
+    pub fn output_string(&self) -> String {
+        self.output
+            .iter()
+            .map(isize::to_string)
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
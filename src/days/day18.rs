@@ -2,12 +2,22 @@
 #![allow(clippy::cast_possible_wrap)]
 use std::{collections::VecDeque, str::FromStr};
 
-use rayon::prelude::*;
-
 use crate::solution::{Solution, SolvedValue};
+use crate::utils::union_find::UnionFind;
 
 type Position = (usize, usize);
 
+/// The first byte after which the end can't be reached anymore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Blockage {
+    /// Index of the byte in the drop list.
+    byte: usize,
+    position: Position,
+    /// Corrupted positions next to the area still reachable from the start,
+    /// sorted by row.
+    boundary: Vec<Position>,
+}
+
 #[derive(Debug)]
 struct Maze {
     drop_list: Vec<Position>,
@@ -64,6 +74,130 @@ impl Maze {
 
         None
     }
+
+    /// Shortest arrival time at the end when byte `i` (counted from 1) lands
+    /// after `i` steps, while the walk is already under way.
+    ///
+    /// The walker may also wait, so the search runs over (position, time)
+    /// states. Bytes never disappear again though, so a state can't be
+    /// better than reaching the same position earlier, and only the earliest
+    /// arrival per position has to be expanded.
+    #[cfg(test)]
+    fn find_arrival_time_with_falling_bytes(&self) -> Option<usize> {
+        let mut falls_at = vec![vec![None; self.size + 1]; self.size + 1];
+        for (byte, &(x, y)) in self.drop_list.iter().enumerate() {
+            falls_at[y][x].get_or_insert(byte + 1);
+        }
+        let free_at = |(x, y): Position, time: usize| falls_at[y][x].is_none_or(|fall| fall > time);
+
+        let mut arrivals = vec![vec![None; self.size + 1]; self.size + 1];
+        arrivals[0][0] = Some(0);
+        let mut queue = VecDeque::from([((0, 0), 0)]);
+        while let Some((position, time)) = queue.pop_front() {
+            if position == (self.size, self.size) {
+                return Some(time);
+            }
+            for (x, y) in self.neighbours(position) {
+                if arrivals[y][x].is_none() && free_at((x, y), time + 1) {
+                    arrivals[y][x] = Some(time + 1);
+                    queue.push_back(((x, y), time + 1));
+                }
+            }
+        }
+        None
+    }
+
+    fn neighbours(&self, (x, y): Position) -> impl Iterator<Item = Position> {
+        let size = self.size;
+        [
+            x.checked_sub(1).map(|x| (x, y)),
+            (x < size).then_some((x + 1, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            (y < size).then_some((x, y + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Finds the blocking byte by taking the bytes away again in reverse
+    /// order and merging the freed positions with their free neighbours,
+    /// until the start and the end end up in the same set.
+    fn first_blocking_byte(&self) -> Option<Blockage> {
+        let width = self.size + 1;
+        let id = |(x, y): Position| y * width + x;
+        let start = id((0, 0));
+        let end = id((self.size, self.size));
+
+        let mut first_drop = vec![None; width * width];
+        for (byte, &position) in self.drop_list.iter().enumerate() {
+            first_drop[id(position)].get_or_insert(byte);
+        }
+        let mut free: Vec<bool> = first_drop.iter().map(Option::is_none).collect();
+        let mut sets = UnionFind::new(width * width);
+        for y in 0..width {
+            for x in 0..width {
+                for neighbour in [(x + 1, y), (x, y + 1)] {
+                    if neighbour.0 < width
+                        && neighbour.1 < width
+                        && free[id((x, y))]
+                        && free[id(neighbour)]
+                    {
+                        sets.union(id((x, y)), id(neighbour));
+                    }
+                }
+            }
+        }
+        if free[start] && free[end] && sets.connected(start, end) {
+            return None;
+        }
+
+        for (byte, &position) in self.drop_list.iter().enumerate().rev() {
+            let cell = id(position);
+            if first_drop[cell] != Some(byte) {
+                continue;
+            }
+            let mut roots: Vec<usize> = self
+                .neighbours(position)
+                .filter(|&neighbour| free[id(neighbour)])
+                .map(|neighbour| sets.find(id(neighbour)))
+                .collect();
+            roots.push(cell);
+            let start_root = sets.find(start);
+            let end_root = sets.find(end);
+            let joins_start = (free[start] && roots.contains(&start_root)) || cell == start;
+            let joins_end = (free[end] && roots.contains(&end_root)) || cell == end;
+            if joins_start && joins_end {
+                return Some(Blockage {
+                    byte,
+                    position,
+                    boundary: self.boundary(&mut sets, &free, start),
+                });
+            }
+            free[cell] = true;
+            for root in roots {
+                sets.union(cell, root);
+            }
+        }
+        None
+    }
+
+    /// Corrupted positions next to the free positions connected to `start`.
+    fn boundary(&self, sets: &mut UnionFind, free: &[bool], start: usize) -> Vec<Position> {
+        let width = self.size + 1;
+        if !free[start] {
+            return Vec::new();
+        }
+        let start_root = sets.find(start);
+        (0..width * width)
+            .map(|cell| (cell % width, cell / width))
+            .filter(|&(x, y)| {
+                !free[y * width + x]
+                    && self.neighbours((x, y)).any(|(nx, ny)| {
+                        free[ny * width + nx] && sets.find(ny * width + nx) == start_root
+                    })
+            })
+            .collect()
+    }
 }
 
 impl FromStr for Maze {
@@ -98,18 +232,8 @@ impl Solution for Day {
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
         let maze: Maze = input.parse().unwrap();
-
-        (1..maze.drop_list.len())
-            .par_bridge()
-            .filter(|i| maze.find_distance_to_end(*i).is_none())
-            .min()
-            .map(|i| {
-                SolvedValue::from(format!(
-                    "{},{}",
-                    maze.drop_list[i - 1].0,
-                    maze.drop_list[i - 1].1
-                ))
-            })
+        let (x, y) = maze.first_blocking_byte()?.position;
+        Some(format!("{x},{y}").into())
     }
 }
 
@@ -142,4 +266,69 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some("65,6".into()));
     }
+
+    #[test]
+    fn test_first_blocking_byte() {
+        for input in [
+            read_input(DAY, true, 2).unwrap(),
+            read_input(DAY, false, 2).unwrap(),
+        ] {
+            let maze: Maze = input.parse().unwrap();
+            let blockage = maze.first_blocking_byte().unwrap();
+            assert!(maze.find_distance_to_end(blockage.byte).is_some());
+            assert!(maze.find_distance_to_end(blockage.byte + 1).is_none());
+
+            // Every boundary position is corrupted and borders the area a
+            // search from the start still reaches
+            let corrupted = maze.get_corrupted_after_bytes(blockage.byte + 1);
+            let mut reached = vec![vec![false; maze.size + 1]; maze.size + 1];
+            let mut stack = vec![(0, 0)];
+            reached[0][0] = true;
+            while let Some(position) = stack.pop() {
+                for (x, y) in maze.neighbours(position) {
+                    if !corrupted[y][x] && !reached[y][x] {
+                        reached[y][x] = true;
+                        stack.push((x, y));
+                    }
+                }
+            }
+            let expected: Vec<Position> = (0..=maze.size)
+                .flat_map(|y| (0..=maze.size).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    corrupted[y][x] && maze.neighbours((x, y)).any(|(nx, ny)| reached[ny][nx])
+                })
+                .collect();
+            assert!(!expected.is_empty());
+            assert_eq!(blockage.boundary, expected);
+        }
+
+        let maze: Maze = "1,1".parse().unwrap();
+        assert_eq!(maze.first_blocking_byte(), None);
+        let maze: Maze = "1,1\n1,0\n0,1\n0,0".parse().unwrap();
+        assert_eq!(
+            maze.first_blocking_byte(),
+            Some(Blockage {
+                byte: 2,
+                position: (0, 1),
+                boundary: vec![(1, 0), (0, 1)],
+            })
+        );
+    }
+
+    #[test]
+    fn test_falling_bytes() {
+        let maze: Maze = read_input(DAY, true, 1).unwrap().parse().unwrap();
+        assert_eq!(maze.find_arrival_time_with_falling_bytes(), Some(12));
+        let maze: Maze = read_input(DAY, false, 1).unwrap().parse().unwrap();
+        assert_eq!(maze.find_arrival_time_with_falling_bytes(), Some(140));
+
+        // A wall in column 3 whose last gap at (3, 6) closes after 10 or 9
+        // steps, the walker gets there after 9
+        let wall = "3,0\n3,1\n3,2\n3,3\n3,4\n3,5\n6,0\n5,0";
+        let maze: Maze = format!("{wall}\n6,1\n3,6").parse().unwrap();
+        assert_eq!(maze.find_distance_to_end(maze.drop_list.len()), None);
+        assert_eq!(maze.find_arrival_time_with_falling_bytes(), Some(12));
+        let maze: Maze = format!("{wall}\n3,6").parse().unwrap();
+        assert_eq!(maze.find_arrival_time_with_falling_bytes(), None);
+    }
 }
@@ -1,12 +1,11 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
-};
-
 use rayon::prelude::*;
 
 use crate::solution::{Solution, SolvedValue};
 
+mod towels;
+
+use towels::Towels;
+
 fn parse_input(input: &str) -> (Vec<&str>, Vec<&str>) {
     let mut lines = input.lines();
     let towels = lines.next().unwrap().split(", ").collect();
@@ -15,52 +14,33 @@ fn parse_input(input: &str) -> (Vec<&str>, Vec<&str>) {
     (towels, designs)
 }
 
-fn design_possible_count(
-    design: &str,
-    towels: &[&str],
-    known_results: &Arc<RwLock<HashMap<String, usize>>>,
-) -> usize {
-    if design.is_empty() {
-        return 1;
-    }
-    if let Some(&result) = known_results.read().unwrap().get(design) {
-        return result;
-    }
-    let result = towels
-        .iter()
-        .filter(|&&towel| design.starts_with(towel))
-        .map(|towel| design_possible_count(&design[towel.len()..], towels, known_results))
-        .sum();
-    if known_results.read().unwrap().get(design).is_none() {
-        known_results
-            .write()
-            .unwrap()
-            .insert(design.to_string(), result);
-    }
-    result
-}
-
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
         let (towels, designs) = parse_input(input);
-        let known_results = Arc::new(RwLock::new(HashMap::new()));
+        let towels = Towels::new(towels);
         let count = designs
             .par_iter()
-            .filter(|design| design_possible_count(design, &towels, &known_results) > 0)
+            .filter(|design| towels.arrangements(design) != Some(0))
             .count();
         Some(count.into())
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
         let (towels, designs) = parse_input(input);
-        let known_results = Arc::new(RwLock::new(HashMap::new()));
-        let count: usize = designs
+        let towels = Towels::new(towels);
+        let count = designs
             .par_iter()
-            .map(|design| design_possible_count(design, &towels, &known_results))
-            .sum();
-        Some(count.into())
+            .map(|design| towels.arrangements(design))
+            .try_reduce(|| 0, u128::checked_add);
+        // Too many arrangements for a number answer are reported as text
+        Some(match count {
+            Some(count) => {
+                usize::try_from(count).map_or_else(|_| count.to_string().into(), Into::into)
+            }
+            None => format!("more than {}", u128::MAX).into(),
+        })
     }
 }
 
@@ -93,4 +73,44 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(632_423_618_484_345.into()));
     }
+
+    #[test]
+    fn test_arrangements() {
+        let input = read_input(DAY, true, 1).unwrap();
+        let (towels, designs) = parse_input(&input);
+        let pieces = towels.clone();
+        let towels = Towels::new(towels);
+        let counts: Vec<Option<u128>> = designs
+            .iter()
+            .map(|design| towels.arrangements(design))
+            .collect();
+        assert_eq!(counts, [2, 1, 4, 6, 0, 1, 2, 0].map(Some));
+        for (design, count) in designs.iter().zip(counts) {
+            let decomposition = towels.decomposition(design);
+            assert_eq!(decomposition.is_some(), count != Some(0));
+            if let Some(decomposition) = decomposition {
+                assert_eq!(decomposition.concat(), *design);
+                assert!(decomposition.iter().all(|towel| pieces.contains(towel)));
+            }
+        }
+
+        // Fibonacci numbers beyond u64
+        let towels = Towels::new(["a", "aa", ""]);
+        let design = "a".repeat(150);
+        assert_eq!(
+            towels.arrangements(&design),
+            Some(16_130_531_424_904_581_415_797_907_386_349)
+        );
+        assert_eq!(towels.decomposition(&design).unwrap().concat(), design);
+        // and beyond u128
+        let design = "a".repeat(200);
+        assert_eq!(towels.arrangements(&design), None);
+        assert_eq!(towels.decomposition(&design).unwrap().concat(), design);
+        assert_eq!(
+            Day.part2(&format!("a, aa\n\n{design}")),
+            Some(format!("more than {}", u128::MAX).into())
+        );
+        assert_eq!(towels.arrangements(""), Some(1));
+        assert_eq!(towels.decomposition("ab"), None);
+    }
 }
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: HashMap<u8, usize>,
+    /// A towel ends here.
+    towel: bool,
+}
+
+/// Trie over the stripe patterns of all towels.
+#[derive(Debug, Clone)]
+pub struct Towels {
+    nodes: Vec<Node>,
+}
+
+impl Towels {
+    pub fn new<'a>(towels: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut nodes = vec![Node::default()];
+        // An empty towel never helps to build a design
+        for towel in towels.into_iter().filter(|towel| !towel.is_empty()) {
+            let mut node = 0;
+            for stripe in towel.bytes() {
+                let next = nodes.len();
+                node = *nodes[node].children.entry(stripe).or_insert(next);
+                if node == next {
+                    nodes.push(Node::default());
+                }
+            }
+            nodes[node].towel = true;
+        }
+        Towels { nodes }
+    }
+
+    /// Lengths of all towels `design` starts with, shortest first.
+    fn prefixes<'a>(&'a self, design: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let mut node = 0;
+        design
+            .iter()
+            .enumerate()
+            .map_while(move |(index, stripe)| {
+                node = *self.nodes[node].children.get(stripe)?;
+                Some(self.nodes[node].towel.then_some(index + 1))
+            })
+            .flatten()
+    }
+
+    /// Walks the design from left to right, every position that can be
+    /// reached passes its number of arrangements on to the ends of the
+    /// towels starting there.
+    ///
+    /// Returns the number of arrangements of every prefix, `None` if it
+    /// doesn't fit a `u128`, and, for each reachable position, a position it
+    /// can be reached from with one towel.
+    fn arrangements_by_position(&self, design: &str) -> (Vec<Option<u128>>, Vec<Option<usize>>) {
+        let design = design.as_bytes();
+        let mut ways = vec![Some(0u128); design.len() + 1];
+        let mut previous = vec![None; design.len() + 1];
+        ways[0] = Some(1);
+        for start in 0..design.len() {
+            if ways[start] == Some(0) {
+                continue;
+            }
+            for length in self.prefixes(&design[start..]) {
+                let end = start + length;
+                ways[end] = ways[end]
+                    .zip(ways[start])
+                    .and_then(|(ways, more)| ways.checked_add(more));
+                previous[end].get_or_insert(start);
+            }
+        }
+        (ways, previous)
+    }
+
+    /// Number of ways to arrange towels into `design`, `None` if there are
+    /// more than fit a `u128`.
+    pub fn arrangements(&self, design: &str) -> Option<u128> {
+        self.arrangements_by_position(design).0[design.len()]
+    }
+
+    /// One arrangement of towels that makes up `design`, if there is any.
+    #[cfg(test)]
+    pub fn decomposition<'a>(&self, design: &'a str) -> Option<Vec<&'a str>> {
+        let (ways, previous) = self.arrangements_by_position(design);
+        if ways[design.len()] == Some(0) {
+            return None;
+        }
+        let mut towels = Vec::new();
+        let mut end = design.len();
+        while end > 0 {
+            let start = previous[end]?;
+            towels.push(&design[start..end]);
+            end = start;
+        }
+        towels.reverse();
+        Some(towels)
+    }
+}
@@ -1,10 +1,9 @@
 #![allow(clippy::cast_possible_wrap)]
 #![allow(clippy::cast_sign_loss)]
 
-use std::{
-    ops::Neg,
-    str::FromStr,
-};
+#[cfg(test)]
+use std::collections::BTreeMap;
+use std::{collections::VecDeque, ops::Neg, str::FromStr};
 
 use rayon::prelude::*;
 
@@ -15,7 +14,17 @@ type Position = (usize, usize);
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Block {
     Wall,
-    Path(Option<usize>),
+    Path,
+}
+
+/// A cheat from the track tile `start` through walls to the track tile
+/// `end`, which arrives `saving` picoseconds earlier than the shortest race
+/// without cheating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cheat {
+    start: Position,
+    end: Position,
+    saving: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -23,78 +32,120 @@ struct Maze {
     tiles: Vec<Vec<Block>>,
     start: Position,
     end: Position,
+    /// Track tiles reachable from the start, the possible cheat starts.
     path: Vec<Position>,
+    from_start: Vec<Vec<Option<usize>>>,
+    from_end: Vec<Vec<Option<usize>>>,
 }
 
 impl Maze {
-    fn next_position(&self, current: Position) -> Position {
+    fn neighbours(&self, current: Position) -> impl Iterator<Item = Position> + '_ {
         [
-            (current.0 + 1, current.1),
-            (current.0, current.1 + 1),
-            (current.0 - 1, current.1),
-            (current.0, current.1 - 1),
+            Some((current.0 + 1, current.1)),
+            Some((current.0, current.1 + 1)),
+            current.0.checked_sub(1).map(|y| (y, current.1)),
+            current.1.checked_sub(1).map(|x| (current.0, x)),
         ]
         .into_iter()
-        .find(|(x, y)| self.tiles[*x][*y] == Block::Path(None))
-        .unwrap()
-    }
-
-    fn get_cheat_neighbors(
-        &self,
-        current: Position,
-        max_distance: isize,
-        min_save: usize,
-    ) -> usize {
-        let Block::Path(Some(current_distance)) = self.tiles[current.0][current.1] else {
-            unreachable!("Should not be a wall");
+        .flatten()
+        .filter(|&(y, x)| self.tiles.get(y).and_then(|row| row.get(x)) == Some(&Block::Path))
+    }
+
+    /// Picoseconds from `source` to every track tile, `None` if unreachable.
+    fn distances_from(&self, source: Position) -> Vec<Vec<Option<usize>>> {
+        let mut distances: Vec<Vec<Option<usize>>> =
+            self.tiles.iter().map(|row| vec![None; row.len()]).collect();
+        distances[source.0][source.1] = Some(0);
+        let mut queue = VecDeque::from([(source, 0)]);
+        while let Some((current, distance)) = queue.pop_front() {
+            for (y, x) in self.neighbours(current) {
+                if distances[y][x].is_none() {
+                    distances[y][x] = Some(distance + 1);
+                    queue.push_back(((y, x), distance + 1));
+                }
+            }
+        }
+        distances
+    }
+
+    /// Cheats starting at `current` that save at least `min_save` (and at
+    /// least one) picoseconds.
+    ///
+    /// A cheat to `(y, x)` takes the shortest way to `current`, the jump and
+    /// the shortest way from `(y, x)` to the end.
+    fn cheats_from(&self, current: Position, max_distance: isize, min_save: usize) -> Vec<Cheat> {
+        let (Some(to_current), Some(best)) = (
+            self.from_start[current.0][current.1],
+            self.from_start[self.end.0][self.end.1],
+        ) else {
+            return Vec::new();
         };
 
-        let mut candidates = 0;
+        let mut cheats = Vec::new();
 
         for i in max_distance.neg()..=max_distance {
-            for j in (max_distance-i.abs()).neg()..=(max_distance-i.abs()) {
+            for j in (max_distance - i.abs()).neg()..=(max_distance - i.abs()) {
                 let y = current.0 as isize + i;
                 let x = current.1 as isize + j;
                 if y < 0 || x < 0 {
                     continue;
                 }
-                let y = y as usize;
-                let x = x as usize;
-                if y >= self.tiles.len() || x >= self.tiles[0].len() {
-                    continue;
-                }
-                if let Block::Path(Some(d)) = self.tiles[y][x] {
-                    if d.saturating_sub(current_distance).saturating_sub(i.unsigned_abs()).saturating_sub(j.unsigned_abs()) >= min_save
-                    {
-                        candidates+=1;
+                let (y, x) = (y as usize, x as usize);
+                // Rows can have different lengths
+                if let Some(&Some(from_target)) = self.from_end.get(y).and_then(|row| row.get(x)) {
+                    let length = to_current + i.unsigned_abs() + j.unsigned_abs() + from_target;
+                    let saving = best.saturating_sub(length);
+                    if saving >= min_save.max(1) {
+                        cheats.push(Cheat {
+                            start: current,
+                            end: (y, x),
+                            saving,
+                        });
                     }
                 }
             }
         }
 
-        candidates
+        cheats
     }
 
+    /// Runs a breadth-first search from the start and from the end, so
+    /// tracks can branch and have dead ends.
     fn calculate_distances(&mut self) {
-        let mut distance = 0;
-        let mut current = self.start;
-        // We know only one path exists
-        while current != self.end {
-            self.tiles[current.0][current.1] = Block::Path(Some(distance));
-            self.path.push(current);
-            distance += 1;
-            current = self.next_position(current);
-        }
-        self.tiles[current.0][current.1] = Block::Path(Some(distance));
-        self.path.push(current);
+        self.from_start = self.distances_from(self.start);
+        self.from_end = self.distances_from(self.end);
+        self.path = (0..self.tiles.len())
+            .flat_map(|y| (0..self.tiles[y].len()).map(move |x| (y, x)))
+            .filter(|&(y, x)| self.from_start[y][x].is_some())
+            .collect();
+    }
+
+    /// Every cheat of at most `max_distance` picoseconds that saves at least
+    /// `min_save` picoseconds.
+    #[cfg(test)]
+    fn cheats(&self, max_distance: isize, min_save: usize) -> Vec<Cheat> {
+        self.path
+            .par_iter()
+            .flat_map_iter(|entry| self.cheats_from(*entry, max_distance, min_save))
+            .collect()
     }
 
     fn find_shortcuts_count(&self, max_distance: isize, min_save: usize) -> usize {
         self.path
             .par_iter()
-            .map(|entry| self.get_cheat_neighbors(*entry, max_distance, min_save))
+            .map(|entry| self.cheats_from(*entry, max_distance, min_save).len())
             .sum()
     }
+
+    /// Number of cheats per saving, like "14 cheats save 4 picoseconds".
+    #[cfg(test)]
+    fn cheat_histogram(&self, max_distance: isize, min_save: usize) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for cheat in self.cheats(max_distance, min_save) {
+            *histogram.entry(cheat.saving).or_default() += 1;
+        }
+        histogram
+    }
 }
 
 impl FromStr for Maze {
@@ -112,14 +163,14 @@ impl FromStr for Maze {
                         row.push(Block::Wall);
                     }
                     '.' => {
-                        row.push(Block::Path(None));
+                        row.push(Block::Path);
                     }
                     'S' => {
-                        row.push(Block::Path(None));
+                        row.push(Block::Path);
                         start = (y, x);
                     }
                     'E' => {
-                        row.push(Block::Path(None));
+                        row.push(Block::Path);
                         end = (y, x);
                     }
                     _ => {
@@ -135,6 +186,8 @@ impl FromStr for Maze {
             start,
             end,
             path: Vec::new(),
+            from_start: Vec::new(),
+            from_end: Vec::new(),
         })
     }
 }
@@ -184,4 +237,94 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(1_010_263.into()));
     }
+
+    #[test]
+    fn test_cheat_histogram() {
+        let mut maze: Maze = read_input(DAY, true, 1).unwrap().parse().unwrap();
+        maze.calculate_distances();
+        assert_eq!(
+            maze.cheat_histogram(2, 1),
+            BTreeMap::from([
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1),
+            ])
+        );
+        assert_eq!(
+            maze.cheat_histogram(20, 50),
+            BTreeMap::from([
+                (50, 32),
+                (52, 31),
+                (54, 29),
+                (56, 39),
+                (58, 25),
+                (60, 23),
+                (62, 20),
+                (64, 19),
+                (66, 12),
+                (68, 14),
+                (70, 12),
+                (72, 22),
+                (74, 4),
+                (76, 3),
+            ])
+        );
+        assert_eq!(maze.find_shortcuts_count(20, 50), 285);
+
+        let cheats = maze.cheats(2, 64);
+        assert_eq!(cheats.len(), 1);
+        let cheat = cheats[0];
+        assert_eq!(cheat.saving, 64);
+        assert_eq!(
+            cheat.start.0.abs_diff(cheat.end.0) + cheat.start.1.abs_diff(cheat.end.1),
+            2
+        );
+    }
+
+    #[test]
+    fn test_branching_track() {
+        // A loop around the middle, a dead end at the bottom border
+        let mut maze: Maze = "###########
+#S..#...#E#
+#.#.#.#.#.#
+#.#...#...#
+#.#####.###
+#.......#.#
+#########.#"
+            .parse()
+            .unwrap();
+        maze.calculate_distances();
+        assert_eq!(maze.from_start[maze.end.0][maze.end.1], Some(16));
+        assert_eq!(maze.cheat_histogram(2, 1), BTreeMap::from([(2, 3), (4, 3)]));
+        assert_eq!(
+            maze.cheat_histogram(3, 1),
+            BTreeMap::from([(2, 10), (4, 8)])
+        );
+        assert_eq!(
+            maze.cheat_histogram(20, 1),
+            BTreeMap::from([(2, 39), (4, 54), (6, 7), (8, 3)])
+        );
+        assert_eq!(maze.find_shortcuts_count(20, 6), 10);
+
+        // Track tiles on every border
+        let mut maze: Maze = "S.#.\n..#E\n....".parse().unwrap();
+        maze.calculate_distances();
+        assert_eq!(maze.from_start[maze.end.0][maze.end.1], Some(6));
+        assert_eq!(maze.cheat_histogram(2, 1), BTreeMap::from([(2, 2)]));
+        assert_eq!(maze.cheat_histogram(20, 1), BTreeMap::from([(2, 6)]));
+
+        // Rows shorter than the first one
+        let mut maze: Maze = "S#E\n...\n#".parse().unwrap();
+        maze.calculate_distances();
+        assert_eq!(maze.from_start[maze.end.0][maze.end.1], Some(4));
+        assert_eq!(maze.cheat_histogram(20, 1), BTreeMap::from([(2, 1)]));
+    }
 }
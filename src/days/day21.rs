@@ -1,116 +1,35 @@
-use std::collections::HashMap;
-
 use crate::solution::{Solution, SolvedValue};
 
-type Position = (usize, usize);
+mod keypad;
+#[cfg(test)]
+mod simulator;
+
+use keypad::Chain;
 
-fn parse_input(input: &str) -> Vec<(usize, Vec<Position>)> {
+fn parse_input(input: &str) -> Vec<(usize, &str)> {
     input
         .lines()
-        .map(|line| {
-            (
-                line.split_once('A').unwrap().0.parse().unwrap(),
-                line.chars()
-                    .map(|c| match c {
-                        'A' => (3, 2),
-                        '0' => (3, 1),
-                        '1' => (2, 0),
-                        '2' => (2, 1),
-                        '3' => (2, 2),
-                        '4' => (1, 0),
-                        '5' => (1, 1),
-                        '6' => (1, 2),
-                        '7' => (0, 0),
-                        '8' => (0, 1),
-                        '9' => (0, 2),
-                        _ => panic!("Invalid input"),
-                    })
-                    .collect(),
-            )
-        })
+        .map(|line| (line.split_once('A').unwrap().0.parse().unwrap(), line))
         .collect()
 }
 
-fn get_directional_keyboard_inputs_for(
-    key: &[Position],
-    start_pos: Position,
-    forbidden: Position,
-    recurse_count: usize,
-    cache: &mut HashMap<(Vec<Position>, Position, Position, usize), usize>,
-) -> usize {
-    if recurse_count == 0 {
-        return key.len();
-    }
-    if let Some(&res) = cache.get(&(key.to_vec(), start_pos, forbidden, recurse_count)) {
-        return res;
-    }
-    let mut pos = start_pos;
-    let mut res = 0;
-    for (ty, tx) in key {
-        let mut key_res = vec![];
-        let v_diff = (0..ty.abs_diff(pos.0))
-            .map(|_| if *ty > pos.0 { (1, 1) } else { (0, 1) })
-            .collect::<Vec<_>>();
-        let h_diff = (0..tx.abs_diff(pos.1))
-            .map(|_| if *tx > pos.1 { (1, 2) } else { (1, 0) })
-            .collect::<Vec<_>>();
-        // Special case forbidden
-        if ((pos.0 == forbidden.0 || *ty == forbidden.0) && pos.0 != *ty)
-            && ((pos.1 == forbidden.1 || *tx == forbidden.1) && pos.1 != *tx)
-        {
-            if *tx < pos.1 {
-                key_res.extend(v_diff);
-                key_res.extend(h_diff);
-            } else {
-                key_res.extend(h_diff);
-                key_res.extend(v_diff);
-            }
-        } else {
-            if *tx < pos.1 {
-                key_res.extend_from_slice(&h_diff);
-            }
-            if *ty != pos.0 {
-                key_res.extend(v_diff);
-            }
-            if *tx > pos.1 {
-                key_res.extend(h_diff);
-            }
-        }
-
-        key_res.push((0, 2));
-        res +=
-            get_directional_keyboard_inputs_for(&key_res, (0, 2), (0, 0), recurse_count - 1, cache);
-        pos = (*ty, *tx);
-    }
-    cache.insert((key.to_vec(), pos, forbidden, recurse_count), res);
-    res
-}
-
-fn get_minimal_combination_for(key: &[Position], dir_bot_count: usize) -> usize {
-    get_directional_keyboard_inputs_for(key, (3, 2), (3, 0), dir_bot_count + 1, &mut HashMap::new())
+fn total_complexity(input: &str, robots: usize) -> Option<usize> {
+    let chain = Chain::robots(robots);
+    parse_input(input)
+        .into_iter()
+        .map(|(complexity, code)| Some(complexity * chain.cost(code).ok()?))
+        .sum()
 }
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let keys = parse_input(input);
-        Some(
-            keys.iter()
-                .map(|(complexity, key)| complexity * get_minimal_combination_for(key, 2))
-                .sum::<usize>()
-                .into(),
-        )
+        Some(total_complexity(input, 2)?.into())
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let keys = parse_input(input);
-        Some(
-            keys.iter()
-                .map(|(complexity, key)| complexity * get_minimal_combination_for(key, 25))
-                .sum::<usize>()
-                .into(),
-        )
+        Some(total_complexity(input, 25)?.into())
     }
 }
 
@@ -119,6 +38,7 @@ mod tests {
     use super::*;
     use crate::solution::Solution;
     use crate::utils::read_input;
+    use keypad::Keypad;
 
     const DAY: usize = 21;
 
@@ -143,4 +63,128 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(260_586_897_262_600.into()));
     }
+
+    #[test]
+    fn test_sequence() {
+        let chain = Chain::robots(0);
+        assert_eq!(chain.sequence("029A"), Ok("<A^A^^>AvvvA".to_string()));
+        assert_eq!(chain.cost("029A"), Ok(12));
+
+        let chain = Chain::robots(2);
+        for (_, code) in parse_input(&read_input(DAY, true, 1).unwrap()) {
+            let sequence = chain.sequence(code).unwrap();
+            assert_eq!(Ok(sequence.len()), chain.cost(code));
+        }
+        assert_eq!(chain.cost("029A"), Ok(68));
+        assert_eq!(
+            chain.cost("02B"),
+            Err("Key B isn't on the door keypad".to_string())
+        );
+    }
+
+    #[test]
+    fn test_custom_keypads() {
+        // Phone layout with the gap in the top left, typed directly
+        let phone = Keypad::parse("#23\n456\n789\n*0A").unwrap();
+        let chain = Chain::new(vec![phone.clone()]).unwrap();
+        assert_eq!(chain.sequence("29A"), Ok("29A".to_string()));
+
+        let directional = Keypad::parse(keypad::DIRECTIONAL).unwrap();
+        let chain = Chain::new(vec![phone.clone(), directional.clone()]).unwrap();
+        assert_eq!(chain.sequence("4A"), Ok("<<^^Avv>>A".to_string()));
+        assert_eq!(chain.sequence("2A"), Ok("<^^^Avvv>A".to_string()));
+
+        let chain = Chain::new(vec![
+            phone,
+            directional.clone(),
+            directional.clone(),
+            directional,
+        ])
+        .unwrap();
+        let sequence = chain.sequence("2580A").unwrap();
+        assert_eq!(Ok(sequence.len()), chain.cost("2580A"));
+
+        assert!(Keypad::parse("12\n3").is_err());
+        assert!(Keypad::parse("1A1").is_err());
+        assert_eq!(
+            Chain::new(vec![
+                Keypad::parse(keypad::NUMERIC).unwrap(),
+                Keypad::parse("A^").unwrap()
+            ])
+            .err(),
+            Some("Directional keypad has no v key".to_string())
+        );
+        assert_eq!(
+            Chain::new(vec![
+                Keypad::parse("A#1").unwrap(),
+                Keypad::parse(keypad::DIRECTIONAL).unwrap()
+            ])
+            .err(),
+            Some("Key A can't be reached from 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_simulator() {
+        let chain = Chain::robots(2);
+        let keypads = chain.keypads();
+        assert_eq!(
+            simulator::simulate(
+                keypads,
+                "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A"
+            ),
+            Ok("029A".to_string())
+        );
+        for (_, code) in parse_input(&read_input(DAY, true, 1).unwrap()) {
+            assert_eq!(
+                simulator::simulate(keypads, &chain.sequence(code).unwrap()),
+                Ok(code.to_string())
+            );
+        }
+
+        let keypads = Chain::robots(0).keypads().to_vec();
+        assert_eq!(simulator::simulate(&keypads, "<A^^A"), Ok("05".to_string()));
+        assert_eq!(
+            simulator::simulate(&keypads, "<A<A"),
+            Err(simulator::SimulationError::Gap {
+                press: 2,
+                keypad: 0
+            })
+        );
+        assert_eq!(
+            simulator::simulate(&keypads, "A1"),
+            Err(simulator::SimulationError::InvalidKey('1'))
+        );
+        assert_eq!(
+            simulator::simulate(Chain::robots(1).keypads(), "<<"),
+            Err(simulator::SimulationError::Gap {
+                press: 1,
+                keypad: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_search_matches_cost() {
+        for robots in 0..=2 {
+            let chain = Chain::robots(robots);
+            for (_, code) in parse_input(&read_input(DAY, true, 1).unwrap()) {
+                assert_eq!(
+                    simulator::shortest_by_search(chain.keypads(), code),
+                    chain.cost(code).ok()
+                );
+            }
+        }
+
+        let phone = Keypad::parse("#23\n456\n789\n*0A").unwrap();
+        let directional = Keypad::parse(keypad::DIRECTIONAL).unwrap();
+        let keypads = vec![phone, directional.clone(), directional.clone(), directional];
+        let chain = Chain::new(keypads.clone()).unwrap();
+        for code in ["2580A", "*A", "93A"] {
+            assert_eq!(
+                simulator::shortest_by_search(&keypads, code),
+                chain.cost(code).ok()
+            );
+        }
+    }
 }
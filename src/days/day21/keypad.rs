@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+/// `(row, column)` of a key.
+pub type Position = (usize, usize);
+
+pub const NUMERIC: &str = "789\n456\n123\n#0A";
+pub const DIRECTIONAL: &str = "#^A\n<v>";
+
+/// A grid of keys, every arm starts on (and presses with) `A`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keypad {
+    rows: Vec<Vec<Option<char>>>,
+    keys: HashMap<char, Position>,
+}
+
+impl Keypad {
+    /// Parses one line per row of keys, `#` or a space marks a gap.
+    pub fn parse(layout: &str) -> Result<Self, String> {
+        let rows: Vec<Vec<Option<char>>> = layout
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|key| (key != '#' && key != ' ').then_some(key))
+                    .collect()
+            })
+            .collect();
+        let mut keys = HashMap::new();
+        for (row, line) in rows.iter().enumerate() {
+            for (column, key) in line.iter().enumerate() {
+                if let Some(key) = key {
+                    if keys.insert(*key, (row, column)).is_some() {
+                        return Err(format!("Key {key} appears twice"));
+                    }
+                }
+            }
+        }
+        if !keys.contains_key(&'A') {
+            return Err("Keypad has no A key".to_string());
+        }
+        Ok(Keypad { rows, keys })
+    }
+
+    pub fn position(&self, key: char) -> Option<Position> {
+        self.keys.get(&key).copied()
+    }
+
+    pub fn key_at(&self, (row, column): Position) -> Option<char> {
+        *self.rows.get(row)?.get(column)?
+    }
+
+    /// Key the arm points at after moving one step from `key` in `direction`
+    /// (`^`, `v`, `<` or `>`), `None` for a gap or the edge of the keypad.
+    #[cfg(test)]
+    pub fn neighbour(&self, key: char, direction: char) -> Option<char> {
+        let (row, column) = self.position(key)?;
+        let next = match direction {
+            '^' => (row.checked_sub(1)?, column),
+            'v' => (row + 1, column),
+            '<' => (row, column.checked_sub(1)?),
+            '>' => (row, column + 1),
+            _ => return None,
+        };
+        self.key_at(next)
+    }
+
+    /// All keys in sorted order.
+    pub fn keys(&self) -> Vec<char> {
+        let mut keys: Vec<char> = self.keys.keys().copied().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Every shortest sequence of `^`, `v`, `<` and `>` from `from` to `to`
+    /// that never points the arm at a gap.
+    fn paths(&self, from: Position, to: Position) -> Vec<String> {
+        if from == to {
+            return vec![String::new()];
+        }
+        let mut steps = Vec::with_capacity(2);
+        if to.1 < from.1 {
+            steps.push(('<', (from.0, from.1 - 1)));
+        }
+        if to.0 < from.0 {
+            steps.push(('^', (from.0 - 1, from.1)));
+        }
+        if to.0 > from.0 {
+            steps.push(('v', (from.0 + 1, from.1)));
+        }
+        if to.1 > from.1 {
+            steps.push(('>', (from.0, from.1 + 1)));
+        }
+        steps
+            .into_iter()
+            .filter(|&(_, next)| self.key_at(next).is_some())
+            .flat_map(|(direction, next)| {
+                self.paths(next, to).into_iter().map(move |path| {
+                    let mut full = direction.to_string();
+                    full.push_str(&path);
+                    full
+                })
+            })
+            .collect()
+    }
+}
+
+/// Keypads from the one at the door to the one the human presses, each but
+/// the last operated by a robot whose arm is moved with the keypad above.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    keypads: Vec<Keypad>,
+    /// Human key presses to move the arm of each keypad between two keys
+    /// and press the second one.
+    costs: Vec<HashMap<(char, char), usize>>,
+}
+
+impl Chain {
+    /// Costs are computed from the human's keypad down, every level only
+    /// needs the costs of the level above it.
+    pub fn new(keypads: Vec<Keypad>) -> Result<Self, String> {
+        let Some(human) = keypads.last() else {
+            return Err("A chain needs at least one keypad".to_string());
+        };
+        for keypad in &keypads[1..] {
+            if let Some(key) = "^v<>".chars().find(|&key| keypad.position(key).is_none()) {
+                return Err(format!("Directional keypad has no {key} key"));
+            }
+        }
+        let mut costs = vec![HashMap::new(); keypads.len()];
+        for from in human.keys() {
+            for to in human.keys() {
+                costs[keypads.len() - 1].insert((from, to), 1);
+            }
+        }
+        for level in (0..keypads.len() - 1).rev() {
+            let keypad = &keypads[level];
+            for from in keypad.keys() {
+                for to in keypad.keys() {
+                    let Some((cost, _)) = best_move(keypad, &costs[level + 1], from, to) else {
+                        return Err(format!("Key {to} can't be reached from {from}"));
+                    };
+                    costs[level].insert((from, to), cost);
+                }
+            }
+        }
+        Ok(Chain { keypads, costs })
+    }
+
+    /// The numeric keypad at the door operated through `robots` directional
+    /// keypads used by robots and the directional keypad of the human.
+    pub fn robots(robots: usize) -> Self {
+        let numeric = Keypad::parse(NUMERIC).unwrap();
+        let directional = Keypad::parse(DIRECTIONAL).unwrap();
+        let mut keypads = vec![numeric];
+        keypads.extend(std::iter::repeat_n(directional, robots + 1));
+        Chain::new(keypads).unwrap()
+    }
+
+    #[cfg(test)]
+    pub fn keypads(&self) -> &[Keypad] {
+        &self.keypads
+    }
+
+    fn check(&self, code: &str) -> Result<(), String> {
+        match code
+            .chars()
+            .find(|&key| self.keypads[0].position(key).is_none())
+        {
+            Some(key) => Err(format!("Key {key} isn't on the door keypad")),
+            None => Ok(()),
+        }
+    }
+
+    /// Fewest key presses of the human to type `code` at the door.
+    pub fn cost(&self, code: &str) -> Result<usize, String> {
+        self.check(code)?;
+        Ok(sequence_cost(&self.costs[0], code))
+    }
+
+    /// A shortest sequence the human can type to enter `code` at the door.
+    ///
+    /// Its length is [`Chain::cost`], which grows exponentially with the
+    /// number of keypads.
+    #[cfg(test)]
+    pub fn sequence(&self, code: &str) -> Result<String, String> {
+        self.check(code)?;
+        Ok(self.expand(0, code))
+    }
+
+    #[cfg(test)]
+    fn expand(&self, level: usize, keys: &str) -> String {
+        if level == self.keypads.len() - 1 {
+            return keys.to_string();
+        }
+        let mut from = 'A';
+        let mut above = String::new();
+        for to in keys.chars() {
+            let (_, path) = best_move(&self.keypads[level], &self.costs[level + 1], from, to)
+                .expect("Every move was checked when building the chain");
+            above.push_str(&path);
+            from = to;
+        }
+        self.expand(level + 1, &above)
+    }
+}
+
+/// Cheapest moves on the keypad above (ending with `A`) that move the arm
+/// of `keypad` from `from` to `to` and press it, with their cost.
+fn best_move(
+    keypad: &Keypad,
+    above: &HashMap<(char, char), usize>,
+    from: char,
+    to: char,
+) -> Option<(usize, String)> {
+    keypad
+        .paths(keypad.keys[&from], keypad.keys[&to])
+        .into_iter()
+        .map(|mut path| {
+            path.push('A');
+            (sequence_cost(above, &path), path)
+        })
+        .min_by_key(|(cost, _)| *cost)
+}
+
+/// Presses to type `keys` on a keypad whose arm starts at `A`.
+fn sequence_cost(costs: &HashMap<(char, char), usize>, keys: &str) -> usize {
+    let mut from = 'A';
+    keys.chars()
+        .map(|to| {
+            let cost = costs[&(from, to)];
+            from = to;
+            cost
+        })
+        .sum()
+}
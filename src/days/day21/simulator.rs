@@ -0,0 +1,100 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
+
+use super::keypad::Keypad;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationError {
+    /// The human pressed a key that isn't on their keypad.
+    InvalidKey(char),
+    /// The `press`-th key of the human (from 0) pointed the arm of a robot
+    /// at a gap of keypad `keypad` (0 is the door).
+    Gap { press: usize, keypad: usize },
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::InvalidKey(key) => write!(f, "Key {key} isn't on the keypad"),
+            SimulationError::Gap { press, keypad } => {
+                write!(
+                    f,
+                    "Press {press} points the arm at a gap of keypad {keypad}"
+                )
+            }
+        }
+    }
+}
+
+/// Applies one press of the human to the arms of the robots, which point at
+/// the keys of `keypads[..keypads.len() - 1]`.
+///
+/// Returns the key pressed at the door, if any, or the keypad whose arm was
+/// pointed at a gap.
+fn press(keypads: &[Keypad], arms: &mut [char], key: char) -> Result<Option<char>, usize> {
+    let mut key = key;
+    for level in (0..arms.len()).rev() {
+        if key == 'A' {
+            key = arms[level];
+        } else {
+            arms[level] = keypads[level].neighbour(arms[level], key).ok_or(level)?;
+            return Ok(None);
+        }
+    }
+    Ok(Some(key))
+}
+
+/// Runs the presses of the human through the robots, every arm starting at
+/// `A`, and returns the code typed at the door.
+pub fn simulate(keypads: &[Keypad], presses: &str) -> Result<String, SimulationError> {
+    let human = keypads.last().expect("At least one keypad");
+    let mut arms = vec!['A'; keypads.len() - 1];
+    let mut code = String::new();
+    for (index, key) in presses.chars().enumerate() {
+        if human.position(key).is_none() {
+            return Err(SimulationError::InvalidKey(key));
+        }
+        match press(keypads, &mut arms, key) {
+            Ok(Some(typed)) => code.push(typed),
+            Ok(None) => {}
+            Err(keypad) => {
+                return Err(SimulationError::Gap {
+                    press: index,
+                    keypad,
+                })
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// Fewest presses to type `code`, by a breadth-first search over the arm
+/// positions of all robots.
+///
+/// Doesn't rely on any move ordering, so it cross-checks the cost tables for
+/// short chains.
+pub fn shortest_by_search(keypads: &[Keypad], code: &str) -> Option<usize> {
+    let code: Vec<char> = code.chars().collect();
+    let human_keys = keypads.last()?.keys();
+    let start = (vec!['A'; keypads.len() - 1], 0);
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some(((arms, typed), presses)) = queue.pop_front() {
+        if typed == code.len() {
+            return Some(presses);
+        }
+        for &key in &human_keys {
+            let mut next_arms = arms.clone();
+            let next_typed = match press(keypads, &mut next_arms, key) {
+                Ok(Some(door_key)) if door_key == code[typed] => typed + 1,
+                Ok(None) => typed,
+                _ => continue,
+            };
+            let next = (next_arms, next_typed);
+            if seen.insert(next.clone()) {
+                queue.push_back((next, presses + 1));
+            }
+        }
+    }
+    None
+}
@@ -1,11 +1,10 @@
-#![allow(clippy::cast_possible_wrap)]
-
-use std::collections::VecDeque;
-
-use rayon::prelude::*;
+#![allow(clippy::cast_possible_truncation)]
 
 use crate::solution::{Solution, SolvedValue};
 
+mod batch;
+mod gf2;
+
 fn mix_and_prune(current: usize, value: usize) -> usize {
     (current ^ value) % 16_777_216
 }
@@ -18,39 +17,59 @@ fn next_secret(current: usize) -> usize {
     secret
 }
 
-fn get_price(secret: usize) -> usize {
-    secret % 10
-}
-
 fn parse_input(input: &str) -> Vec<usize> {
     input.lines().map(|line| line.parse().unwrap()).collect()
 }
 
-fn get_diff_index(x1: isize, x2: isize, x3: isize, x4: isize) -> usize {
-    ((x1 + 10) * 20isize.pow(3) + (x2 + 10) * 20isize.pow(2) + (x3 + 10) * 20isize + (x4 + 10))
-        .try_into()
-        .unwrap()
-}
-
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
+        let jump = gf2::Matrix::next_secret().pow(2000);
         Some(
             parse_input(input)
-                .into_par_iter()
-                .map(|secret| (0..2000).fold(secret, |secret, _| next_secret(secret)))
+                .into_iter()
+                .map(|secret| {
+                    // Only the low 24 bits of a seed affect the next secret
+                    let secret = u32::try_from(secret % 16_777_216).expect("Masked to 24 bits");
+                    jump.apply(secret) as usize
+                })
                 .sum::<usize>()
                 .into(),
         )
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let start_secrets = parse_input(input);
+        Some(batch::best_sequence_total(&parse_input(input), 2000).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::Solution;
+    use crate::utils::read_input;
+    use std::collections::VecDeque;
+
+    const DAY: usize = 22;
+
+    fn get_price(secret: usize) -> usize {
+        secret % 10
+    }
+
+    fn get_diff_index(x1: isize, x2: isize, x3: isize, x4: isize) -> usize {
+        ((x1 + 10) * 20isize.pow(3) + (x2 + 10) * 20isize.pow(2) + (x3 + 10) * 20isize + (x4 + 10))
+            .try_into()
+            .unwrap()
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn best_sequence_total_scalar(start_secrets: &[usize]) -> usize {
         let mut max = 0;
         let mut global_diffs = vec![0usize; 20usize.pow(4)];
 
-        for mut secret in start_secrets {
+        for &secret in start_secrets {
+            let mut secret = secret;
             let mut last_price = get_price(secret);
             let mut diffs = VecDeque::with_capacity(4);
             let mut seen = vec![false; 20usize.pow(4)];
@@ -71,17 +90,29 @@ impl Solution for Day {
                 }
             }
         }
-        Some(max.into())
+        max
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::solution::Solution;
-    use crate::utils::read_input;
 
-    const DAY: usize = 22;
+    #[test]
+    fn test_batch_matches_scalar() {
+        let mut secrets = parse_input(&read_input(DAY, false, 1).unwrap());
+        // Not a multiple of the lane count, so the last chunk is padded
+        secrets.truncate(301);
+        secrets.extend([0, 1, 16_777_215]);
+        // Seeds above 24 bits, the last one above 32 bits
+        secrets.extend([16_777_216, 16_777_216 * 7 + 3, (1 << 32) + 12_345]);
+        let scalar: Vec<usize> = secrets
+            .iter()
+            .map(|&secret| (0..2000).fold(secret, |secret, _| next_secret(secret)))
+            .collect();
+        assert_eq!(batch::evolve(&secrets, 2000), scalar);
+        let masked: Vec<usize> = secrets.iter().map(|secret| secret % 16_777_216).collect();
+        assert_eq!(batch::evolve(&secrets, 0), masked);
+        assert_eq!(
+            batch::best_sequence_total(&secrets, 2000),
+            best_sequence_total_scalar(&secrets)
+        );
+    }
 
     #[test]
     fn test_part1_example() {
@@ -104,4 +135,33 @@ mod tests {
         let input = read_input(DAY, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(1784.into()));
     }
+
+    #[test]
+    fn test_jump_and_rewind() {
+        let step = gf2::Matrix::next_secret();
+        let mut secret = 123;
+        for steps in 0..=20 {
+            assert_eq!(step.pow(steps).apply(123) as usize, secret);
+            assert_eq!(gf2::rewind(secret, steps), 123);
+            secret = next_secret(secret);
+        }
+        assert_eq!(step.pow(10).apply(123), 5_908_254);
+        assert_eq!(gf2::rewind(5_908_254, 10), 123);
+
+        let inverse = step.inverse().unwrap();
+        assert_eq!(inverse.after(&step), gf2::Matrix::identity());
+        assert_eq!(step.after(&inverse), gf2::Matrix::identity());
+        for secret in [0, 1, 42, 16_777_215] {
+            assert_eq!(inverse.apply(next_secret(secret) as u32) as usize, secret);
+        }
+
+        let secrets = parse_input(&read_input(DAY, false, 1).unwrap());
+        let jump = step.pow(2000);
+        let jumped: Vec<usize> = secrets
+            .iter()
+            .map(|&secret| jump.apply(secret as u32) as usize)
+            .collect();
+        assert_eq!(jumped, batch::evolve(&secrets, 2000));
+        assert_eq!(step.pow(2000).after(&step.pow(1000)), step.pow(3000));
+    }
 }
@@ -0,0 +1,123 @@
+use rayon::prelude::*;
+
+/// Number of secrets advanced in lockstep.
+pub const LANES: usize = 8;
+
+const MASK: u32 = (1 << 24) - 1;
+/// Four price changes of -9 to 9, each stored as a base 20 digit.
+const SEQUENCES: usize = 20usize.pow(4);
+
+type Lanes = [u32; LANES];
+
+/// Advances every lane by one secret.
+///
+/// The same xor-shifts on each element of a fixed size array, which the
+/// compiler turns into vector instructions on any target that has them.
+fn step(secrets: &mut Lanes) {
+    for secret in secrets.iter_mut() {
+        *secret ^= (*secret << 6) & MASK;
+        *secret ^= *secret >> 5;
+        *secret ^= (*secret << 11) & MASK;
+    }
+}
+
+/// Loads up to [`LANES`] secrets, unused lanes start (and stay) at zero.
+///
+/// Only the low 24 bits of a secret affect the next one, so larger seeds are
+/// masked like the prune step would.
+fn load(chunk: &[usize]) -> Lanes {
+    let mut secrets = [0; LANES];
+    for (lane, &secret) in secrets.iter_mut().zip(chunk) {
+        *lane = u32::try_from(secret & MASK as usize).expect("Masked to 24 bits");
+    }
+    secrets
+}
+
+/// Every secret after `steps` steps, the cross-check for the jump matrix.
+#[cfg(test)]
+pub fn evolve(secrets: &[usize], steps: usize) -> Vec<usize> {
+    secrets
+        .par_chunks(LANES)
+        .flat_map_iter(|chunk| {
+            let mut lanes = load(chunk);
+            for _ in 0..steps {
+                step(&mut lanes);
+            }
+            lanes
+                .into_iter()
+                .take(chunk.len())
+                .map(|secret| secret as usize)
+        })
+        .collect()
+}
+
+/// Banana totals per sequence of four price changes for one thread.
+struct Tables {
+    totals: Vec<u32>,
+    /// Generation in which each lane last sold on a sequence, so the buffer
+    /// never has to be cleared between chunks of buyers.
+    seen: Vec<Lanes>,
+    generation: u32,
+}
+
+impl Tables {
+    fn new() -> Self {
+        Tables {
+            totals: vec![0; SEQUENCES],
+            seen: vec![[0; LANES]; SEQUENCES],
+            generation: 0,
+        }
+    }
+
+    /// Adds the first sale of every sequence of each buyer in `chunk`.
+    fn add_buyers(&mut self, chunk: &[usize], steps: usize) {
+        self.generation += 1;
+        let mut secrets = load(chunk);
+        // The first price is taken from the seed before masking
+        let mut prices = [0; LANES];
+        for (price, &secret) in prices.iter_mut().zip(chunk) {
+            *price = (secret % 10) as u32;
+        }
+        let mut keys = [0; LANES];
+        for change in 0..steps {
+            step(&mut secrets);
+            for lane in 0..chunk.len() {
+                let price = secrets[lane] % 10;
+                let digit = (prices[lane] + 10 - price) as usize;
+                keys[lane] = (keys[lane] * 20 + digit) % SEQUENCES;
+                prices[lane] = price;
+                let key = keys[lane];
+                if change >= 3 && self.seen[key][lane] != self.generation {
+                    self.seen[key][lane] = self.generation;
+                    self.totals[key] += price;
+                }
+            }
+        }
+    }
+}
+
+/// Most bananas a single sequence of four price changes can buy, every
+/// buyer selling at the first occurrence of the sequence.
+///
+/// Threads fill their own tables, which are summed at the end.
+pub fn best_sequence_total(secrets: &[usize], steps: usize) -> usize {
+    secrets
+        .par_chunks(LANES)
+        .fold(Tables::new, |mut tables, chunk| {
+            tables.add_buyers(chunk, steps);
+            tables
+        })
+        .map(|tables| tables.totals)
+        .reduce(
+            || vec![0; SEQUENCES],
+            |mut totals, other| {
+                for (total, other) in totals.iter_mut().zip(other) {
+                    *total += other;
+                }
+                totals
+            },
+        )
+        .into_iter()
+        .max()
+        .unwrap_or(0) as usize
+}
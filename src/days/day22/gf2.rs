@@ -0,0 +1,97 @@
+#![allow(clippy::cast_possible_truncation)]
+
+use super::next_secret;
+
+const BITS: usize = 24;
+
+/// Linear map on 24 bit vectors over GF(2), stored column by column.
+///
+/// Column `j` is the image of the vector with only bit `j` set, so applying
+/// the map xors together the columns of all set bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Matrix {
+    columns: [u32; BITS],
+}
+
+impl Matrix {
+    pub fn identity() -> Self {
+        Matrix {
+            columns: std::array::from_fn(|bit| 1 << bit),
+        }
+    }
+
+    /// Matrix of `next_secret`, which only mixes in shifted copies of the
+    /// secret with xor and masks them, so it's linear.
+    pub fn next_secret() -> Self {
+        Matrix {
+            columns: std::array::from_fn(|bit| next_secret(1 << bit) as u32),
+        }
+    }
+
+    pub fn apply(&self, vector: u32) -> u32 {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| vector & (1 << bit) != 0)
+            .fold(0, |result, (_, column)| result ^ column)
+    }
+
+    /// `self` applied after `other`.
+    pub fn after(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            columns: other.columns.map(|column| self.apply(column)),
+        }
+    }
+
+    /// The map applied `exponent` times, by repeated squaring.
+    pub fn pow(&self, mut exponent: usize) -> Matrix {
+        let mut result = Matrix::identity();
+        let mut square = *self;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = square.after(&result);
+            }
+            square = square.after(&square);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// Inverse by Gauss-Jordan elimination, `None` if the map isn't bijective.
+    #[cfg(test)]
+    pub fn inverse(&self) -> Option<Matrix> {
+        // Row `i` holds bit `i` of every column, with the identity appended
+        // in the upper half.
+        let mut rows: [u64; BITS] = std::array::from_fn(|row| {
+            let bits = (0..BITS)
+                .filter(|&column| self.columns[column] & (1 << row) != 0)
+                .fold(0u64, |bits, column| bits | 1 << column);
+            bits | 1 << (BITS + row)
+        });
+        for column in 0..BITS {
+            let pivot = (column..BITS).find(|&row| rows[row] & (1 << column) != 0)?;
+            rows.swap(column, pivot);
+            for row in 0..BITS {
+                if row != column && rows[row] & (1 << column) != 0 {
+                    rows[row] ^= rows[column];
+                }
+            }
+        }
+        Some(Matrix {
+            columns: std::array::from_fn(|column| {
+                (0..BITS)
+                    .filter(|&row| rows[row] & (1 << (BITS + column)) != 0)
+                    .fold(0, |bits, row| bits | 1 << row)
+            }),
+        })
+    }
+}
+
+/// The secret that turns into `secret` after `steps` steps.
+#[cfg(test)]
+pub fn rewind(secret: usize, steps: usize) -> usize {
+    let inverse = Matrix::next_secret()
+        .inverse()
+        .expect("Every secret has exactly one predecessor");
+    inverse.pow(steps).apply(secret as u32) as usize
+}
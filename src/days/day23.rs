@@ -1,89 +1,34 @@
-use std::collections::{HashMap, HashSet};
-
 use crate::solution::{Solution, SolvedValue};
 
-type ConnectionMap<'a> = HashMap<&'a str, Vec<&'a str>>;
-
-fn parse_input(input: &str) -> ConnectionMap {
-    let mut res: HashMap<&str, Vec<&str>> = HashMap::new();
-    for connection in input.lines() {
-        let parties = connection.split_once('-').unwrap();
-        res.entry(parties.0).or_default().push(parties.1);
-        res.entry(parties.1).or_default().push(parties.0);
-    }
-    res
-}
-
-fn is_pairwise_connected(connections: &ConnectionMap, partners: &[&str]) -> bool {
-    for i in 0..partners.len() {
-        for j in i + 1..partners.len() {
-            if !connections.get(partners[i]).unwrap().contains(&partners[j]) {
-                return false;
-            }
-        }
-    }
-    true
-}
+mod bitset;
+mod clique;
+mod graph;
 
-fn build_groups_of_size<'a>(
-    connections: &'a ConnectionMap<'a>,
-    size: usize,
-    start: &'a str,
-) -> HashSet<Vec<&'a str>> {
-    if size == 0 {
-        return HashSet::new();
-    }
-    if size == 1 {
-        return HashSet::from_iter([vec![start]]);
-    }
-    let mut groups: HashSet<Vec<&str>> = HashSet::new();
-    for other in connections.get(start).unwrap() {
-        let sub_groups = build_groups_of_size(connections, size - 1, other);
-        for sub_group in sub_groups {
-            let mut group = vec![start];
-            group.extend(sub_group);
-            if is_pairwise_connected(connections, &group) {
-                group.sort_unstable();
-                groups.insert(group);
-            }
-        }
-    }
-    groups
-}
+use graph::Graph;
 
-fn biggest_pairwise_group<'a>(connections: &'a ConnectionMap) -> Vec<&'a str> {
-    let mut biggest_group: Option<Vec<&str>> = None;
-    for (&party, partners) in connections {
-        let mut group = vec![party];
-        for &partner in partners {
-            group.push(partner);
-            if is_pairwise_connected(connections, &group)
-                && group.len() > biggest_group.as_ref().map_or(0, Vec::len)
-            {
-                biggest_group = Some(group.clone());
-            }
-        }
-    }
-    biggest_group.unwrap().clone()
+fn parse_input(input: &str) -> Graph<'_> {
+    let edges: Vec<(&str, &str)> = input
+        .lines()
+        .map(|connection| connection.split_once('-').unwrap())
+        .collect();
+    Graph::new(&edges)
 }
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let connections = parse_input(input);
-        let mut t_groups = HashSet::new();
-        for start in connections.keys().filter(|k| k.starts_with('t')) {
-            t_groups.extend(build_groups_of_size(&connections, 3, start));
-        }
-        Some(t_groups.len().into())
+        let graph = parse_input(input);
+        Some(graph.count_cliques(3, |name| name.starts_with('t')).into())
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let connections = parse_input(input);
-        let mut biggest_group = biggest_pairwise_group(&connections);
-        biggest_group.sort_unstable();
-        Some(biggest_group.join(",").into())
+        let graph = parse_input(input);
+        let party: Vec<&str> = clique::maximum_clique(graph.adjacency())
+            .into_iter()
+            .map(|id| graph.name(id))
+            .collect();
+        Some(party.join(",").into())
     }
 }
 
@@ -114,6 +59,87 @@ mod tests {
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some("ah,ap,ek,fj,fr,jt,ka,ln,me,mp,qa,ql,zg".into()));
+        assert_eq!(
+            Day.part2(&input),
+            Some("ah,ap,ek,fj,fr,jt,ka,ln,me,mp,qa,ql,zg".into())
+        );
+    }
+
+    #[test]
+    fn test_maximal_cliques() {
+        let input = read_input(DAY, true, 2).unwrap();
+        let graph = parse_input(&input);
+        let adjacency = graph.adjacency();
+        let by_name = |cliques: Vec<Vec<usize>>| {
+            let mut joined: Vec<String> = cliques
+                .into_iter()
+                .map(|clique| {
+                    clique
+                        .iter()
+                        .map(|&id| graph.name(id))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .collect();
+            joined.sort();
+            joined
+        };
+
+        let all = clique::maximal_cliques(adjacency, 0);
+        assert!(all.iter().all(|clique| {
+            clique
+                .iter()
+                .all(|&a| clique.iter().all(|&b| a == b || graph.has_edge(a, b)))
+        }));
+        assert!(all.iter().all(|clique| {
+            (0..graph.len()).all(|node| {
+                clique.contains(&node) || clique.iter().any(|&member| !graph.has_edge(node, member))
+            })
+        }));
+        assert_eq!(
+            by_name(clique::maximal_cliques(adjacency, 4)),
+            vec!["co,de,ka,ta"]
+        );
+        assert_eq!(
+            clique::maximal_cliques(adjacency, 3).len(),
+            all.iter().filter(|clique| clique.len() >= 3).count()
+        );
+        assert_eq!(
+            by_name(vec![clique::maximum_clique(adjacency)]),
+            vec!["co,de,ka,ta"]
+        );
+
+        // Two triangles joined by an edge, ids follow the names
+        let graph = parse_input("aa-bb\nbb-cc\naa-cc\ncc-dd\ndd-ee\nee-ff\ndd-ff");
+        let adjacency = graph.adjacency();
+        let mut cliques = clique::maximal_cliques(adjacency, 0);
+        cliques.sort();
+        assert_eq!(cliques, vec![vec![0, 1, 2], vec![2, 3], vec![3, 4, 5]]);
+        let mut cliques = clique::maximal_cliques(adjacency, 3);
+        cliques.sort();
+        assert_eq!(cliques, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+        assert_eq!(clique::maximum_clique(adjacency), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_graph() {
+        let input = read_input(DAY, true, 1).unwrap();
+        let graph = parse_input(&input);
+        assert_eq!(graph.len(), 16);
+        let (co, de, ka, ta) = ["co", "de", "ka", "ta"]
+            .map(|name| graph.id(name).unwrap())
+            .into();
+        assert!(graph.has_edge(co, de) && graph.has_edge(de, co) && graph.has_edge(ka, ta));
+        assert!(!graph.has_edge(co, co));
+        assert_eq!(graph.id("xx"), None);
+
+        assert_eq!(graph.count_cliques(3, |_| true), 12);
+        assert_eq!(graph.count_cliques(3, |name| name.starts_with('t')), 7);
+        assert_eq!(graph.count_cliques(4, |_| true), 1);
+        assert_eq!(graph.count_cliques(4, |name| name == "wq"), 0);
+        assert_eq!(graph.count_cliques(5, |_| true), 0);
+        assert_eq!(graph.count_cliques(1, |name| name.starts_with('t')), 4);
+        assert_eq!(graph.count_cliques(2, |_| true), 32);
+        assert_eq!(graph.count_cliques(0, |_| true), 0);
     }
 }
@@ -0,0 +1,115 @@
+use super::bitset::BitSet;
+
+/// Node of `candidates ∪ excluded` with the most neighbours in `candidates`.
+///
+/// Only the candidates that aren't adjacent to the pivot have to be branched
+/// on, every clique containing one of its neighbours also contains the pivot
+/// or one of these candidates.
+fn pivot(adjacency: &[BitSet], candidates: &BitSet, excluded: &BitSet) -> Option<usize> {
+    candidates
+        .iter()
+        .chain(excluded.iter())
+        .max_by_key(|&node| {
+            (
+                candidates.intersection_len(&adjacency[node]),
+                usize::MAX - node,
+            )
+        })
+}
+
+/// All maximal cliques with at least `min_size` nodes, each sorted by id.
+///
+/// Bron–Kerbosch with pivoting over the bitset adjacency of the nodes
+/// `0..adjacency.len()`.
+#[cfg(test)]
+pub fn maximal_cliques(adjacency: &[BitSet], min_size: usize) -> Vec<Vec<usize>> {
+    let mut cliques = Vec::new();
+    let mut clique = Vec::new();
+    let mut report = |clique: &[usize]| {
+        if clique.len() >= min_size {
+            let mut clique = clique.to_vec();
+            clique.sort_unstable();
+            cliques.push(clique);
+        }
+    };
+    bron_kerbosch(
+        adjacency,
+        &mut clique,
+        BitSet::full(adjacency.len()),
+        BitSet::new(adjacency.len()),
+        &|clique, candidates| clique + candidates >= min_size,
+        &mut report,
+    );
+    cliques
+}
+
+/// A largest clique, sorted by id.
+///
+/// Of several largest cliques the first one the search runs into is
+/// returned, which only depends on the node ids.
+pub fn maximum_clique(adjacency: &[BitSet]) -> Vec<usize> {
+    let mut best: Vec<usize> = Vec::new();
+    let best_len = std::cell::Cell::new(0);
+    let mut clique = Vec::new();
+    bron_kerbosch(
+        adjacency,
+        &mut clique,
+        BitSet::full(adjacency.len()),
+        BitSet::new(adjacency.len()),
+        // Branches that can't beat the best clique so far are cut off
+        &|clique, candidates| clique + candidates > best_len.get(),
+        &mut |clique| {
+            if clique.len() > best.len() {
+                best = clique.to_vec();
+                best_len.set(best.len());
+            }
+        },
+    );
+    best.sort_unstable();
+    best
+}
+
+/// Extends `clique` by the `candidates` that aren't `excluded` yet.
+///
+/// `worthwhile` gets the size of the clique and the number of candidates and
+/// prunes the branch if it returns `false`. `report` is called with every
+/// maximal clique that isn't pruned.
+fn bron_kerbosch(
+    adjacency: &[BitSet],
+    clique: &mut Vec<usize>,
+    mut candidates: BitSet,
+    mut excluded: BitSet,
+    worthwhile: &dyn Fn(usize, usize) -> bool,
+    report: &mut dyn FnMut(&[usize]),
+) {
+    if candidates.is_empty() {
+        if excluded.is_empty() {
+            report(clique);
+        }
+        return;
+    }
+    let Some(pivot) = pivot(adjacency, &candidates, &excluded) else {
+        return;
+    };
+    for node in candidates
+        .difference(&adjacency[pivot])
+        .iter()
+        .collect::<Vec<_>>()
+    {
+        if !worthwhile(clique.len(), candidates.len()) {
+            return;
+        }
+        clique.push(node);
+        bron_kerbosch(
+            adjacency,
+            clique,
+            candidates.intersection(&adjacency[node]),
+            excluded.intersection(&adjacency[node]),
+            worthwhile,
+            report,
+        );
+        clique.pop();
+        candidates.remove(node);
+        excluded.insert(node);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use super::bitset::BitSet;
+
+/// Undirected graph with node names interned into dense ids.
+///
+/// Ids follow the name order and every node keeps its neighbours as a
+/// bitset, so edge queries are a single bit lookup.
+#[derive(Debug, Clone)]
+pub struct Graph<'a> {
+    names: Vec<&'a str>,
+    adjacency: Vec<BitSet>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new(edges: &[(&'a str, &'a str)]) -> Self {
+        let mut names: Vec<&str> = edges.iter().flat_map(|&(a, b)| [a, b]).collect();
+        names.sort_unstable();
+        names.dedup();
+        let ids: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(id, &name)| (name, id))
+            .collect();
+        let mut adjacency = vec![BitSet::new(names.len()); names.len()];
+        for (a, b) in edges {
+            adjacency[ids[a]].insert(ids[b]);
+            adjacency[ids[b]].insert(ids[a]);
+        }
+        Graph { names, adjacency }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    #[cfg(test)]
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names.binary_search(&name).ok()
+    }
+
+    pub fn name(&self, id: usize) -> &'a str {
+        self.names[id]
+    }
+
+    #[cfg(test)]
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.adjacency[a].contains(b)
+    }
+
+    /// Neighbour sets indexed by node id.
+    pub fn adjacency(&self) -> &[BitSet] {
+        &self.adjacency
+    }
+
+    /// Number of cliques with exactly `size` nodes of which at least one has
+    /// a name matching `predicate`.
+    ///
+    /// Cliques are only built in ascending id order, so each one is counted
+    /// once without collecting them.
+    pub fn count_cliques(&self, size: usize, predicate: impl Fn(&str) -> bool) -> usize {
+        let matching: Vec<bool> = self.names.iter().map(|name| predicate(name)).collect();
+        self.count_extensions(&BitSet::full(self.len()), size, false, &matching)
+    }
+
+    /// Ways to pick `missing` more nodes out of `candidates`, all of which are
+    /// adjacent to the clique built so far.
+    fn count_extensions(
+        &self,
+        candidates: &BitSet,
+        missing: usize,
+        matched: bool,
+        matching: &[bool],
+    ) -> usize {
+        if missing == 0 {
+            return usize::from(matched);
+        }
+        if candidates.len() < missing {
+            return 0;
+        }
+        let mut candidates = candidates.clone();
+        let mut count = 0;
+        for node in candidates.clone().iter() {
+            candidates.remove(node);
+            count += self.count_extensions(
+                &candidates.intersection(&self.adjacency[node]),
+                missing - 1,
+                matched || matching[node],
+                matching,
+            );
+        }
+        count
+    }
+}
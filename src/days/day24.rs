@@ -1,9 +1,17 @@
 #![allow(clippy::too_many_lines)]
+#![allow(clippy::cast_possible_truncation)]
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use crate::solution::{Solution, SolvedValue};
 
+#[cfg(test)]
+mod export;
+mod netlist;
+mod repair;
+
+use netlist::{GateKind, Netlist, NetlistError};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum LogicGate {
     And(String, String),
@@ -12,28 +20,6 @@ enum LogicGate {
 }
 
 impl LogicGate {
-    fn eval(
-        &self,
-        wire_states: &mut HashMap<String, bool>,
-        gates: &HashMap<String, LogicGate>,
-        visited: &mut HashSet<String>,
-    ) -> bool {
-        match self {
-            LogicGate::And(a, b) => {
-                get_wire_state(wire_states, gates, a.clone(), visited)
-                    && get_wire_state(wire_states, gates, b.clone(), visited)
-            }
-            LogicGate::Or(a, b) => {
-                get_wire_state(wire_states, gates, a.clone(), visited)
-                    || get_wire_state(wire_states, gates, b.clone(), visited)
-            }
-            LogicGate::Xor(a, b) => {
-                get_wire_state(wire_states, gates, a.clone(), visited)
-                    ^ get_wire_state(wire_states, gates, b.clone(), visited)
-            }
-        }
-    }
-
     fn parse(s: &str) -> Self {
         let parts = s.split(' ').collect::<Vec<_>>();
         match *parts.get(1).unwrap() {
@@ -44,17 +30,29 @@ impl LogicGate {
         }
     }
 
-    fn values(&self) -> (String, String) {
+    fn kind(&self) -> GateKind {
         match self {
-            Self::And(a, b) | Self::Or(a, b) | Self::Xor(a, b) => (a.to_owned(), b.to_owned()),
+            Self::And(_, _) => GateKind::And,
+            Self::Or(_, _) => GateKind::Or,
+            Self::Xor(_, _) => GateKind::Xor,
         }
     }
 
-    fn is_direct(&self) -> bool {
-        self.values().0.starts_with('x') || self.values().1.starts_with('x')
+    fn inputs(&self) -> [&str; 2] {
+        match self {
+            Self::And(a, b) | Self::Or(a, b) | Self::Xor(a, b) => [a, b],
+        }
     }
 }
 
+fn build_netlist(gates: &HashMap<String, LogicGate>) -> Result<Netlist, NetlistError> {
+    Netlist::new(
+        gates
+            .iter()
+            .map(|(output, gate)| (gate.kind(), gate.inputs(), output.as_str())),
+    )
+}
+
 fn parse_input(input: &str) -> (HashMap<String, bool>, HashMap<String, LogicGate>) {
     let (initial, gate_setup) = input.split_once("\n\n").unwrap();
 
@@ -75,161 +73,21 @@ fn parse_input(input: &str) -> (HashMap<String, bool>, HashMap<String, LogicGate
     (map, gates)
 }
 
-fn get_wire_state(
-    wire_states: &mut HashMap<String, bool>,
-    gates: &HashMap<String, LogicGate>,
-    wire: String,
-    visited: &mut HashSet<String>,
-) -> bool {
-    if visited.contains(&wire) {
-        return false;
-    }
-    if let Some(state) = wire_states.get(&wire) {
-        return *state;
-    }
-
-    // println!("Evaluating {wire}");
-    // println!("{:?}", gates.get(wire));
-
-    visited.insert(wire.clone());
-    let gate = gates.get(&wire).unwrap();
-    let res = gate.eval(wire_states, gates, visited);
-    wire_states.insert(wire, res);
-    res
-}
-
-fn get_system_var(
-    wire_states: &mut HashMap<String, bool>,
-    gates: &HashMap<String, LogicGate>,
-    var: char,
-) -> usize {
-    let mut res = 0;
-    let mut var_keys: Vec<String> = gates
-        .keys()
-        .filter(|k| k.starts_with(var))
-        .cloned()
-        .collect();
-    var_keys.extend(
-        wire_states
-            .keys()
-            .filter(|k| k.starts_with(var))
-            .cloned()
-            .collect::<Vec<_>>(),
-    );
-    var_keys.sort_unstable();
-    for (i, var_key) in var_keys.into_iter().enumerate() {
-        if get_wire_state(wire_states, gates, var_key, &mut HashSet::new()) {
-            res |= 1 << i;
-        }
-    }
-    res
-}
-
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let (mut wire_states, gates) = parse_input(input);
-        Some(get_system_var(&mut wire_states, &gates, 'z').into())
+        let (wire_states, gates) = parse_input(input);
+        let netlist = build_netlist(&gates).ok()?;
+        let values = netlist.evaluate_single(&wire_states);
+        Some((netlist::read_lane(&values, netlist.bus('z'), 0) as usize).into())
     }
 
     fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let (wires, gates) = parse_input(input);
-
-        let mut flagged_gates = HashSet::new();
-
-        let gates0: Vec<_> = gates
-            .iter()
-            .filter(|(_, gate)| gate.is_direct())
-            .filter(|(_, gate)| matches!(gate, LogicGate::Xor(_, _)))
-            .collect();
-
-        flagged_gates.extend(
-            gates0
-                .iter()
-                .filter(|(output, gate)| {
-                    if gate.values().0 == "x00" || gate.values().1 == "x00" {
-                        *output != "z00"
-                    } else if *output == "z00" {
-                        true
-                    } else {
-                        output.starts_with('z')
-                    }
-                })
-                .map(|(output, _)| (*output).to_string()),
-        );
-
-        let gates3: Vec<_> = gates
-            .iter()
-            .filter(|(_, gate)| matches!(gate, LogicGate::Xor(_, _)))
-            .filter(|(_, gate)| !gate.is_direct())
-            .collect();
-
-        flagged_gates.extend(
-            gates3
-                .iter()
-                .filter(|(output, _)| !output.starts_with('z'))
-                .map(|(output, _)| (*output).to_string()),
-        );
-
-        flagged_gates.extend(
-            gates
-                .iter()
-                .filter(|(output, gate)| {
-                    if !output.starts_with('z') {
-                        return false;
-                    }
-                    let is_last = *output == &format!("z{}", wires.len() / 2);
-                    if is_last {
-                        !matches!(gate, LogicGate::Or(_, _))
-                    } else {
-                        !matches!(gate, LogicGate::Xor(_, _))
-                    }
-                })
-                .map(|(output, _)| output.to_string()),
-        );
-
-        let mut check_next = vec![];
-        for (output, gate) in gates0 {
-            if output == "z00" || flagged_gates.contains(output) {
-                continue;
-            }
-            if !gates3
-                .iter()
-                .any(|(_, og)| &og.values().0 == output || &og.values().1 == output)
-            {
-                check_next.push((output, gate));
-                flagged_gates.insert(output.clone());
-            }
-        }
-
-        flagged_gates.extend(check_next.iter().map(|(_, gate)| {
-            let intended_result = format!("z{}", &gate.values().0[1..]);
-            let matched = gates3
-                .iter()
-                .find(|(output, _)| *output == &intended_result)
-                .unwrap();
-
-            let or_gate = gates
-                .iter()
-                .filter(|(_, gate)| matches!(gate, LogicGate::Or(_, _)))
-                .find(|(output, _)| {
-                    *output == &matched.1.values().0 || *output == &matched.1.values().1
-                })
-                .unwrap();
-
-            if &matched.1.values().0 != or_gate.0 {
-                matched.1.values().0
-            } else if &matched.1.values().1 != or_gate.0 {
-                matched.1.values().1
-            } else {
-                unreachable!("Should never be reached")
-            }
-        }));
-
-        let mut wrong_gates = Vec::from_iter(flagged_gates);
-        wrong_gates.sort();
-        Some(wrong_gates.join(",").into())
+        let (_, gates) = parse_input(input);
+        let netlist = build_netlist(&gates).ok()?;
+        let swapped = repair::repair(&netlist, 4).ok()?;
+        Some(swapped.join(",").into())
     }
 }
 
@@ -238,6 +96,7 @@ mod tests {
     use super::*;
     use crate::solution::Solution;
     use crate::utils::read_input;
+    use std::collections::HashSet;
 
     const DAY: usize = 24;
 
@@ -255,6 +114,164 @@ mod tests {
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some("gwh,jct,rcb,wbw,wgb,z09,z21,z39".into()));
+        assert_eq!(
+            Day.part2(&input),
+            Some("gwh,jct,rcb,wbw,wgb,z09,z21,z39".into())
+        );
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let (_, gates) = parse_input(
+            "x00: 1\ny00: 0\n\nx00 AND abc -> def\ndef OR y00 -> ghi\nghi XOR x00 -> abc\nx00 OR y00 -> z00",
+        );
+        let Err(NetlistError::Cycle(cycle)) = build_netlist(&gates) else {
+            panic!("cycle not detected");
+        };
+        assert_eq!(cycle.len(), 4);
+        assert_eq!(cycle.first(), cycle.last());
+        let mut wires = cycle[1..].to_vec();
+        wires.sort();
+        assert_eq!(wires, vec!["abc", "def", "ghi"]);
+
+        assert_eq!(
+            Netlist::new([
+                (GateKind::And, ["x00", "y00"], "z00"),
+                (GateKind::Or, ["x00", "y00"], "z00"),
+            ])
+            .err(),
+            Some(NetlistError::MultipleDrivers("z00".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_batch_evaluation() {
+        let (_, gates) = parse_input(&read_input(DAY, false, 1).unwrap());
+        let netlist = build_netlist(&gates).unwrap();
+        let pairs: Vec<(u64, u64)> = (0..200u64)
+            .map(|i| {
+                (
+                    i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 20,
+                    (i * i * 7_919) % (1 << 44),
+                )
+            })
+            .collect();
+        let batch = netlist.add_batch(&pairs);
+        for (&(x, y), z) in pairs.iter().zip(batch) {
+            let inputs: HashMap<String, bool> = (0..45)
+                .flat_map(|bit| {
+                    [
+                        (format!("x{bit:02}"), (x >> bit) & 1 == 1),
+                        (format!("y{bit:02}"), (y >> bit) & 1 == 1),
+                    ]
+                })
+                .collect();
+            let values = netlist.evaluate_single(&inputs);
+            assert_eq!(netlist::read_lane(&values, netlist.bus('z'), 0), z);
+        }
+    }
+
+    #[test]
+    fn test_repair() {
+        let (_, gates) = parse_input(&read_input(DAY, false, 2).unwrap());
+        let netlist = build_netlist(&gates).unwrap();
+        assert_eq!(
+            repair::repair(&netlist, 3),
+            Err(repair::RepairError::NoRepair)
+        );
+
+        let wire = |name| netlist.wire(name).unwrap();
+        let fixed = [
+            ("gwh", "z09"),
+            ("wbw", "wgb"),
+            ("rcb", "z21"),
+            ("jct", "z39"),
+        ]
+        .into_iter()
+        .try_fold(netlist.clone(), |netlist, (a, b)| {
+            netlist.with_swapped_outputs(wire(a), wire(b))
+        })
+        .unwrap();
+        assert_eq!(repair::repair(&fixed, 0), Ok(vec![]));
+
+        let broken = fixed
+            .with_swapped_outputs(wire("z30"), wire("z31"))
+            .unwrap();
+        assert_eq!(
+            repair::repair(&broken, 1),
+            Ok(vec!["z30".to_string(), "z31".to_string()])
+        );
+        assert_eq!(
+            repair::repair(&broken, 0),
+            Err(repair::RepairError::NoRepair)
+        );
+
+        let (_, gates) = parse_input(&read_input(DAY, true, 1).unwrap());
+        assert!(matches!(
+            repair::repair(&build_netlist(&gates).unwrap(), 4),
+            Err(repair::RepairError::NotAnAdder(_))
+        ));
+    }
+
+    #[test]
+    fn test_export() {
+        let (_, gates) = parse_input(
+            "x00: 1\nx01: 0\ny00: 1\ny01: 1\n\nx00 XOR y00 -> z00\nx00 AND y00 -> c00\nx01 XOR y01 -> s01\ns01 XOR c00 -> z02\nx01 AND y01 -> a01\ns01 AND c00 -> b01\na01 OR b01 -> z01",
+        );
+        let highlighted = HashSet::from(["z01".to_string(), "z02".to_string()]);
+        let dot = export::to_dot(&gates, &highlighted);
+        assert!(dot.starts_with("digraph circuit {\n"));
+        assert!(dot.contains("    x00 [shape=box];\n"));
+        assert!(dot.contains("    c00 [label=\"AND\\nc00\", shape=ellipse];\n"));
+        assert!(dot.contains(
+            "    z01 [label=\"OR\\nz01\", shape=doubleoctagon, style=filled, fillcolor=\"#ff8080\", color=red];\n"
+        ));
+        assert!(dot.contains("        label=\"bit 0\";\n        x00; y00; z00;\n"));
+        assert!(dot.contains("        label=\"bit 1\";\n        x01; y01; z01;\n"));
+        assert!(dot.contains("        label=\"bit 2\";\n        z02;\n"));
+        assert!(dot.contains("    s01 -> z02;\n    c00 -> z02;\n"));
+        assert_eq!(dot, export::to_dot(&gates, &highlighted));
+
+        assert_eq!(
+            export::to_verilog(&gates, "adder"),
+            "module adder (
+    input x00,
+    input x01,
+    input y00,
+    input y01,
+    output z00,
+    output z01,
+    output z02
+);
+    wire a01;
+    wire b01;
+    wire c00;
+    wire s01;
+    and g0 (a01, x01, y01);
+    and g1 (b01, s01, c00);
+    and g2 (c00, x00, y00);
+    xor g3 (s01, x01, y01);
+    xor g4 (z00, x00, y00);
+    or g5 (z01, a01, b01);
+    xor g6 (z02, s01, c00);
+endmodule
+"
+        );
+
+        // Wires named like Verilog keywords are written as escaped identifiers
+        let (_, gates) = parse_input("x00: 1\ny00: 0\n\nx00 AND y00 -> and\nand XOR x00 -> reg");
+        assert_eq!(
+            export::to_verilog(&gates, "or"),
+            "module \\or  (
+    input x00,
+    input y00
+);
+    wire \\and ;
+    wire \\reg ;
+    and g0 (\\and , x00, y00);
+    xor g1 (\\reg , \\and , x00);
+endmodule
+"
+        );
     }
 }
@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use super::netlist::GateKind;
+use super::LogicGate;
+
+/// Bit number of bus wires like `x07`, `y07` or `z07`.
+fn bus_bit(wire: &str) -> Option<usize> {
+    let (prefix, digits) = wire.split_at(1);
+    if !matches!(prefix, "x" | "y" | "z") || digits.is_empty() {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Wires read by some gate without being driven by one, sorted by name.
+fn primary_inputs(gates: &HashMap<String, LogicGate>) -> BTreeSet<&str> {
+    gates
+        .values()
+        .flat_map(LogicGate::inputs)
+        .filter(|wire| !gates.contains_key(*wire))
+        .collect()
+}
+
+fn sorted_gates(gates: &HashMap<String, LogicGate>) -> Vec<(&str, &LogicGate)> {
+    let mut sorted: Vec<_> = gates
+        .iter()
+        .map(|(output, gate)| (output.as_str(), gate))
+        .collect();
+    sorted.sort_unstable_by_key(|(output, _)| *output);
+    sorted
+}
+
+/// Graphviz DOT graph with one node per wire.
+///
+/// Gate nodes are named after their output wire and labelled with their
+/// kind. The `x`, `y` and `z` wires of each bit share a cluster, and gates
+/// driving a wire in `highlighted` (e.g. the swapped outputs) are filled red.
+pub fn to_dot(gates: &HashMap<String, LogicGate>, highlighted: &HashSet<String>) -> String {
+    let inputs = primary_inputs(gates);
+    let gates = sorted_gates(gates);
+
+    let mut bits: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for wire in inputs
+        .iter()
+        .copied()
+        .chain(gates.iter().map(|(output, _)| *output))
+    {
+        if let Some(bit) = bus_bit(wire) {
+            bits.entry(bit).or_default().push(wire);
+        }
+    }
+
+    let mut dot = String::new();
+    dot.push_str("digraph circuit {\n    rankdir=LR;\n    node [fontname=\"monospace\"];\n");
+    for wire in &inputs {
+        let _ = writeln!(dot, "    {wire} [shape=box];");
+    }
+    for (output, gate) in &gates {
+        let kind = match gate.kind() {
+            GateKind::And => "AND",
+            GateKind::Or => "OR",
+            GateKind::Xor => "XOR",
+        };
+        let shape = if output.starts_with('z') {
+            "doubleoctagon"
+        } else {
+            "ellipse"
+        };
+        let style = if highlighted.contains(*output) {
+            ", style=filled, fillcolor=\"#ff8080\", color=red"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            dot,
+            "    {output} [label=\"{kind}\\n{output}\", shape={shape}{style}];"
+        );
+    }
+    for (bit, wires) in &bits {
+        let _ = writeln!(
+            dot,
+            "    subgraph cluster_bit{bit:02} {{\n        label=\"bit {bit}\";\n        {};\n    }}",
+            wires.join("; ")
+        );
+    }
+    for (output, gate) in &gates {
+        for input in gate.inputs() {
+            let _ = writeln!(dot, "    {input} -> {output};");
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Reserved words of Verilog (IEEE 1364-2005).
+const VERILOG_KEYWORDS: &str = "\
+    always and assign automatic begin buf bufif0 bufif1 case casex casez cell \
+    cmos config deassign default defparam design disable edge else end endcase \
+    endconfig endfunction endgenerate endmodule endprimitive endspecify endtable \
+    endtask event for force forever fork function generate genvar highz0 highz1 \
+    if ifnone incdir include initial inout input instance integer join large \
+    liblist library localparam macromodule medium module nand negedge nmos nor \
+    noshowcancelled not notif0 notif1 or output parameter pmos posedge primitive \
+    pull0 pull1 pulldown pullup pulsestyle_ondetect pulsestyle_onevent rcmos \
+    real realtime reg release repeat rnmos rpmos rtran rtranif0 rtranif1 \
+    scalared showcancelled signed small specify specparam strong0 strong1 \
+    supply0 supply1 table task time tran tranif0 tranif1 tri tri0 tri1 triand \
+    trior trireg unsigned use uwire vectored wait wand weak0 weak1 while wire \
+    wor xnor xor";
+
+/// The wire name as a Verilog identifier, escaped (`\name `) if it is a
+/// keyword or contains characters a simple identifier can't.
+fn verilog_identifier(wire: &str) -> Cow<'_, str> {
+    let simple = wire.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && wire
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if simple
+        && !VERILOG_KEYWORDS
+            .split_whitespace()
+            .any(|keyword| keyword == wire)
+    {
+        Cow::Borrowed(wire)
+    } else {
+        Cow::Owned(format!("\\{wire} "))
+    }
+}
+
+/// Structural Verilog module built from gate primitives.
+///
+/// Every undriven wire becomes an input port, every `z` wire an output port
+/// and all other gate outputs internal wires.
+pub fn to_verilog(gates: &HashMap<String, LogicGate>, module: &str) -> String {
+    let inputs = primary_inputs(gates);
+    let gates = sorted_gates(gates);
+    let (outputs, wires): (Vec<&str>, Vec<&str>) = gates
+        .iter()
+        .map(|(output, _)| *output)
+        .partition(|output| output.starts_with('z'));
+
+    let ports: Vec<String> = inputs
+        .iter()
+        .map(|wire| format!("    input {}", verilog_identifier(wire)))
+        .chain(
+            outputs
+                .iter()
+                .map(|wire| format!("    output {}", verilog_identifier(wire))),
+        )
+        .collect();
+    let mut verilog = format!(
+        "module {} (\n{}\n);\n",
+        verilog_identifier(module),
+        ports.join(",\n")
+    );
+    for wire in &wires {
+        let _ = writeln!(verilog, "    wire {};", verilog_identifier(wire));
+    }
+    for (index, (output, gate)) in gates.iter().enumerate() {
+        let primitive = match gate.kind() {
+            GateKind::And => "and",
+            GateKind::Or => "or",
+            GateKind::Xor => "xor",
+        };
+        let [a, b] = gate.inputs().map(verilog_identifier);
+        let output = verilog_identifier(output);
+        let _ = writeln!(verilog, "    {primitive} g{index} ({output}, {a}, {b});");
+    }
+    verilog.push_str("endmodule\n");
+    verilog
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    And,
+    Or,
+    Xor,
+}
+
+impl GateKind {
+    fn apply(self, a: u64, b: u64) -> u64 {
+        match self {
+            GateKind::And => a & b,
+            GateKind::Or => a | b,
+            GateKind::Xor => a ^ b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gate {
+    pub kind: GateKind,
+    pub inputs: [usize; 2],
+    pub output: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetlistError {
+    /// Wires forming a loop, each one feeding the gate of the next one.
+    Cycle(Vec<String>),
+    MultipleDrivers(String),
+}
+
+impl Display for NetlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetlistError::Cycle(wires) => write!(f, "Combinational cycle: {}", wires.join(" -> ")),
+            NetlistError::MultipleDrivers(wire) => write!(f, "Wire {wire} has multiple drivers"),
+        }
+    }
+}
+
+/// A circuit with interned wire names and gates in topological order.
+///
+/// Wire values are `u64` lanes, so one evaluation runs 64 independent
+/// input vectors at once.
+#[derive(Debug, Clone)]
+pub struct Netlist {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    gates: Vec<Gate>,
+    /// Wires by the first letter of their name, sorted by name.
+    buses: HashMap<char, Vec<usize>>,
+}
+
+impl Netlist {
+    pub fn new<'a>(
+        gates: impl IntoIterator<Item = (GateKind, [&'a str; 2], &'a str)>,
+    ) -> Result<Self, NetlistError> {
+        let mut netlist = Netlist {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            gates: Vec::new(),
+            buses: HashMap::new(),
+        };
+        let gates = gates
+            .into_iter()
+            .map(|(kind, [a, b], output)| Gate {
+                kind,
+                inputs: [netlist.intern(a), netlist.intern(b)],
+                output: netlist.intern(output),
+            })
+            .collect::<Vec<_>>();
+        netlist.gates = netlist.sorted(&gates)?;
+        for (wire, name) in netlist.names.iter().enumerate() {
+            if let Some(prefix) = name.chars().next() {
+                netlist.buses.entry(prefix).or_default().push(wire);
+            }
+        }
+        for bus in netlist.buses.values_mut() {
+            bus.sort_unstable_by_key(|&wire| &netlist.names[wire]);
+        }
+        Ok(netlist)
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    /// Kahn's algorithm over the gates.
+    fn sorted(&self, gates: &[Gate]) -> Result<Vec<Gate>, NetlistError> {
+        let mut driver = vec![None; self.names.len()];
+        for (index, gate) in gates.iter().enumerate() {
+            if driver[gate.output].replace(index).is_some() {
+                return Err(NetlistError::MultipleDrivers(
+                    self.names[gate.output].clone(),
+                ));
+            }
+        }
+        let mut consumers = vec![Vec::new(); gates.len()];
+        let mut pending_inputs = vec![0; gates.len()];
+        for (index, gate) in gates.iter().enumerate() {
+            for input in gate.inputs {
+                if let Some(source) = driver[input] {
+                    consumers[source].push(index);
+                    pending_inputs[index] += 1;
+                }
+            }
+        }
+        let mut ready: Vec<usize> = (0..gates.len())
+            .filter(|&index| pending_inputs[index] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(gates.len());
+        while let Some(index) = ready.pop() {
+            order.push(gates[index]);
+            for &consumer in &consumers[index] {
+                pending_inputs[consumer] -= 1;
+                if pending_inputs[consumer] == 0 {
+                    ready.push(consumer);
+                }
+            }
+        }
+        if order.len() == gates.len() {
+            return Ok(order);
+        }
+
+        // Every gate left over has an input driven by another left over gate,
+        // so walking backwards along those inputs has to run into a loop.
+        let mut index = (0..gates.len())
+            .find(|&index| pending_inputs[index] > 0)
+            .unwrap();
+        let mut path = Vec::new();
+        let mut position = vec![None; gates.len()];
+        while position[index].is_none() {
+            position[index] = Some(path.len());
+            path.push(index);
+            index = gates[index]
+                .inputs
+                .iter()
+                .filter_map(|&input| driver[input])
+                .find(|&source| pending_inputs[source] > 0)
+                .unwrap();
+        }
+        let mut cycle: Vec<String> = path[position[index].unwrap()..]
+            .iter()
+            .rev()
+            .map(|&index| self.names[gates[index].output].clone())
+            .collect();
+        cycle.push(cycle[0].clone());
+        Err(NetlistError::Cycle(cycle))
+    }
+
+    pub fn wire(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, wire: usize) -> &str {
+        &self.names[wire]
+    }
+
+    pub fn wire_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Gates in topological order.
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    /// Copy of the netlist with the drivers of the two wires exchanged.
+    #[cfg(test)]
+    pub fn with_swapped_outputs(&self, a: usize, b: usize) -> Result<Self, NetlistError> {
+        let mut netlist = self.clone();
+        netlist.swap_outputs(a, b)?;
+        Ok(netlist)
+    }
+
+    /// Exchanges the drivers of the two wires in place.
+    ///
+    /// The gates keep their order if it is still topological, otherwise they
+    /// are sorted again. On an error the netlist is left unchanged.
+    pub fn swap_outputs(&mut self, a: usize, b: usize) -> Result<(), NetlistError> {
+        let swap = |gates: &mut [Gate]| {
+            for gate in gates {
+                if gate.output == a {
+                    gate.output = b;
+                } else if gate.output == b {
+                    gate.output = a;
+                }
+            }
+        };
+        swap(&mut self.gates);
+        if !self.is_ordered() {
+            match self.sorted(&self.gates) {
+                Ok(gates) => self.gates = gates,
+                Err(error) => {
+                    swap(&mut self.gates);
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every gate comes after the gates driving its inputs.
+    fn is_ordered(&self) -> bool {
+        let mut pending = vec![false; self.names.len()];
+        for gate in &self.gates {
+            pending[gate.output] = true;
+        }
+        self.gates.iter().all(|gate| {
+            let ready = gate.inputs.iter().all(|&input| !pending[input]);
+            pending[gate.output] = false;
+            ready
+        })
+    }
+
+    /// Wires starting with `prefix`, least significant bit first.
+    pub fn bus(&self, prefix: char) -> &[usize] {
+        self.buses.get(&prefix).map_or(&[], Vec::as_slice)
+    }
+
+    /// Evaluates all gates, `values` has to hold the inputs already.
+    pub fn evaluate(&self, values: &mut [u64]) {
+        for gate in &self.gates {
+            values[gate.output] = gate
+                .kind
+                .apply(values[gate.inputs[0]], values[gate.inputs[1]]);
+        }
+    }
+
+    /// Evaluates with the given input wires set in every lane.
+    pub fn evaluate_single(&self, inputs: &HashMap<String, bool>) -> Vec<u64> {
+        let mut values = vec![0; self.names.len()];
+        for (name, &value) in inputs {
+            if let Some(wire) = self.wire(name) {
+                values[wire] = if value { u64::MAX } else { 0 };
+            }
+        }
+        self.evaluate(&mut values);
+        values
+    }
+
+    /// Feeds every pair into the `x` and `y` buses and reads the `z` bus.
+    ///
+    /// Pairs are evaluated 64 at a time, one per bit lane.
+    pub fn add_batch(&self, pairs: &[(u64, u64)]) -> Vec<u64> {
+        let (x_bus, y_bus, z_bus) = (self.bus('x'), self.bus('y'), self.bus('z'));
+        let mut results = Vec::with_capacity(pairs.len());
+        let mut values = vec![0; self.names.len()];
+        for chunk in pairs.chunks(64) {
+            values.fill(0);
+            for (lane, &(x, y)) in chunk.iter().enumerate() {
+                write_lane(&mut values, x_bus, lane, x);
+                write_lane(&mut values, y_bus, lane, y);
+            }
+            self.evaluate(&mut values);
+            results.extend((0..chunk.len()).map(|lane| read_lane(&values, z_bus, lane)));
+        }
+        results
+    }
+}
+
+fn write_lane(values: &mut [u64], bus: &[usize], lane: usize, number: u64) {
+    for (bit, &wire) in bus.iter().enumerate() {
+        values[wire] |= ((number >> bit) & 1) << lane;
+    }
+}
+
+/// Reads the number on `bus` in the given lane.
+pub fn read_lane(values: &[u64], bus: &[usize], lane: usize) -> u64 {
+    bus.iter().enumerate().fold(0, |number, (bit, &wire)| {
+        number | (((values[wire] >> lane) & 1) << bit)
+    })
+}
@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use super::netlist::{GateKind, Netlist};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairError {
+    /// The circuit doesn't have the shape of a ripple-carry adder.
+    NotAnAdder(String),
+    NoRepair,
+}
+
+impl Display for RepairError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepairError::NotAnAdder(reason) => write!(f, "Not a ripple-carry adder: {reason}"),
+            RepairError::NoRepair => write!(f, "No set of swaps turns the circuit into an adder"),
+        }
+    }
+}
+
+const RANDOM_CHECKS: usize = 4096;
+
+/// Checks the gate counts of a ripple-carry adder and returns its width.
+///
+/// A half adder for bit 0 needs one XOR and one AND, every following full
+/// adder two XORs, two ANDs and one OR.
+fn adder_width(netlist: &Netlist) -> Result<usize, RepairError> {
+    let not_an_adder = |reason: String| Err(RepairError::NotAnAdder(reason));
+    let width = netlist.bus('x').len();
+    if width == 0 || width > 63 {
+        return not_an_adder(format!("{width} input bits"));
+    }
+    if netlist.bus('y').len() != width || netlist.bus('z').len() != width + 1 {
+        return not_an_adder("x, y and z buses don't match".to_string());
+    }
+    let count = |kind| {
+        netlist
+            .gates()
+            .iter()
+            .filter(|gate| gate.kind == kind)
+            .count()
+    };
+    let expected = [
+        (GateKind::Xor, 2 * width - 1),
+        (GateKind::And, 2 * width - 1),
+        (GateKind::Or, width - 1),
+    ];
+    for (kind, expected) in expected {
+        if count(kind) != expected {
+            return not_an_adder(format!("expected {expected} {kind:?} gates"));
+        }
+    }
+    Ok(width)
+}
+
+/// Every combination of the input bits `bit - 1` and `bit`, which covers the
+/// sum and carry logic of that bit.
+fn bit_vectors(bit: usize) -> Vec<(u64, u64)> {
+    let low = bit.saturating_sub(1);
+    (0..16u64)
+        .map(|combination| {
+            let x = ((combination & 1) << low) | (((combination >> 1) & 1) << bit);
+            let y = (((combination >> 2) & 1) << low) | (((combination >> 3) & 1) << bit);
+            (x, y)
+        })
+        .collect()
+}
+
+/// Lowest bit whose test vectors don't add up correctly.
+fn lowest_failing_bit(netlist: &Netlist, width: usize) -> Option<usize> {
+    // Four bits fill the 64 lanes of one evaluation
+    (0..width).step_by(4).find_map(|first_bit| {
+        let bits = first_bit..(first_bit + 4).min(width);
+        let vectors: Vec<_> = bits.clone().flat_map(bit_vectors).collect();
+        netlist
+            .add_batch(&vectors)
+            .into_iter()
+            .zip(&vectors)
+            .position(|(z, &(x, y))| x + y != z)
+            .map(|index| first_bit + index / 16)
+    })
+}
+
+/// Pseudo random additions, all of which have to be correct.
+fn passes_random_checks(netlist: &Netlist, width: usize) -> bool {
+    let mask = (1 << width) - 1;
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state & mask
+    };
+    let vectors: Vec<_> = (0..RANDOM_CHECKS).map(|_| (next(), next())).collect();
+    netlist
+        .add_batch(&vectors)
+        .into_iter()
+        .zip(&vectors)
+        .all(|(z, &(x, y))| x + y == z)
+}
+
+/// Highest input bit each wire depends on.
+fn wire_levels(netlist: &Netlist) -> Vec<Option<usize>> {
+    let mut levels = vec![None; netlist.wire_count()];
+    for prefix in ['x', 'y'] {
+        for (bit, &wire) in netlist.bus(prefix).iter().enumerate() {
+            levels[wire] = Some(bit);
+        }
+    }
+    for gate in netlist.gates() {
+        levels[gate.output] = levels[gate.inputs[0]].max(levels[gate.inputs[1]]);
+    }
+    levels
+}
+
+/// Output wires that could be involved in a fault first visible at `bit`.
+fn candidates(netlist: &Netlist, bit: usize) -> Vec<usize> {
+    let levels = wire_levels(netlist);
+    let mut candidates: Vec<usize> = netlist
+        .gates()
+        .iter()
+        .map(|gate| gate.output)
+        .filter(|&wire| levels[wire].is_some_and(|level| level + 1 >= bit && level <= bit + 1))
+        .collect();
+    if let Some(outputs) = netlist.bus('z').get(bit..=bit + 1) {
+        candidates.extend_from_slice(outputs);
+    }
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+}
+
+/// Finds exactly `pairs` output swaps that make the circuit a working
+/// ripple-carry adder and returns the names of all swapped wires.
+///
+/// Swaps are searched bit by bit: the lowest bit that adds up wrong limits
+/// both wires of a swap to gates close to that bit, and a swap is only kept
+/// if it moves the first failure further up. A fault that needs two swaps
+/// before its bit adds up (or one swap with a wire far from the bit) is
+/// therefore not found and gives [`RepairError::NoRepair`]. The final
+/// circuit is verified with every bit pattern per bit and a batch of random
+/// additions.
+pub fn repair(netlist: &Netlist, pairs: usize) -> Result<Vec<String>, RepairError> {
+    let width = adder_width(netlist)?;
+    let mut swapped = Vec::new();
+    if search(&mut netlist.clone(), width, pairs, &mut swapped) {
+        let mut names: Vec<String> = swapped
+            .iter()
+            .flat_map(|&(a, b)| [netlist.name(a).to_string(), netlist.name(b).to_string()])
+            .collect();
+        names.sort();
+        Ok(names)
+    } else {
+        Err(RepairError::NoRepair)
+    }
+}
+
+/// Tries swaps on `netlist` in place and undoes every swap it backtracks
+/// from.
+fn search(
+    netlist: &mut Netlist,
+    width: usize,
+    pairs: usize,
+    swapped: &mut Vec<(usize, usize)>,
+) -> bool {
+    let Some(bit) = lowest_failing_bit(netlist, width) else {
+        return swapped.len() == pairs && passes_random_checks(netlist, width);
+    };
+    if swapped.len() == pairs {
+        return false;
+    }
+    let used: HashSet<usize> = swapped.iter().flat_map(|&(a, b)| [a, b]).collect();
+    let candidates: Vec<usize> = candidates(netlist, bit)
+        .into_iter()
+        .filter(|wire| !used.contains(wire))
+        .collect();
+    for (index, &a) in candidates.iter().enumerate() {
+        for &b in &candidates[index + 1..] {
+            if netlist.swap_outputs(a, b).is_err() {
+                continue;
+            }
+            if lowest_failing_bit(netlist, width).is_none_or(|new_bit| new_bit > bit) {
+                swapped.push((a, b));
+                if search(netlist, width, pairs, swapped) {
+                    return true;
+                }
+                swapped.pop();
+            }
+            netlist
+                .swap_outputs(a, b)
+                .expect("Swapping back restores a valid netlist");
+        }
+    }
+    false
+}
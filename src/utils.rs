@@ -1,6 +1,10 @@
 use std::fs::read_to_string;
 use std::path::Path;
 
+pub mod cycle;
+pub mod math;
+pub mod union_find;
+
 #[must_use]
 pub fn read_input(day: usize, example: bool, part: u8) -> Option<String> {
     read_to_string(
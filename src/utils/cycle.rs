@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A cycle in the sequence `x0, f(x0), f(f(x0)), ...`.
+///
+/// The state at step `start` is the first one that repeats and it repeats
+/// every `length` steps from then on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Maps any step to the earliest step that has the same state.
+    #[must_use]
+    pub fn normalize(&self, step: usize) -> usize {
+        if step < self.start {
+            step
+        } else {
+            self.start + (step - self.start) % self.length
+        }
+    }
+}
+
+/// Floyd's tortoise and hare. Only keeps two states in memory.
+pub fn floyd<T, F>(initial: &T, step: F) -> Cycle
+where
+    T: Clone + Eq,
+    F: Fn(&T) -> T,
+{
+    let mut tortoise = step(initial);
+    let mut hare = step(&step(initial));
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    let mut start = 0;
+    tortoise = initial.clone();
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Brent's algorithm. Needs fewer calls to `step` than [`floyd`].
+pub fn brent<T, F>(initial: &T, step: F) -> Cycle
+where
+    T: Clone + Eq,
+    F: Fn(&T) -> T,
+{
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    let mut tortoise = initial.clone();
+    let mut hare = initial.clone();
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Runs the sequence until a state repeats and keeps every state seen.
+///
+/// Returns the cycle and the history of all states from step `0` up to (but
+/// excluding) the first repetition, so `history[cycle.normalize(n)]` is the
+/// state at any step `n`.
+pub fn find_with_history<T, F>(initial: T, step: F) -> (Cycle, Vec<T>)
+where
+    T: Clone + Hash + Eq,
+    F: Fn(&T) -> T,
+{
+    let mut seen = HashMap::new();
+    let mut history = Vec::new();
+    let mut current = initial;
+    loop {
+        if let Some(&start) = seen.get(&current) {
+            let length = history.len() - start;
+            return (Cycle { start, length }, history);
+        }
+        seen.insert(current.clone(), history.len());
+        let next = step(&current);
+        history.push(current);
+        current = next;
+    }
+}
+
+/// Returns the state after `n` steps, skipping ahead as soon as a cycle is
+/// found.
+pub fn fast_forward<T, F>(initial: T, step: F, n: usize) -> T
+where
+    T: Clone + Hash + Eq,
+    F: Fn(&T) -> T,
+{
+    let mut seen = HashMap::new();
+    let mut history = Vec::new();
+    let mut current = initial;
+    for i in 0..n {
+        if let Some(&start) = seen.get(&current) {
+            let cycle = Cycle {
+                start,
+                length: i - start,
+            };
+            return history.swap_remove(cycle.normalize(n));
+        }
+        seen.insert(current.clone(), i);
+        let next = step(&current);
+        history.push(current);
+        current = next;
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 6 -> 3 -> ...
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn step(x: &usize) -> usize {
+        if *x == 6 {
+            3
+        } else {
+            x + 1
+        }
+    }
+
+    #[test]
+    fn test_floyd() {
+        assert_eq!(
+            floyd(&0, step),
+            Cycle {
+                start: 3,
+                length: 4
+            }
+        );
+        assert_eq!(
+            floyd(&5, step),
+            Cycle {
+                start: 0,
+                length: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_brent() {
+        assert_eq!(
+            brent(&0, step),
+            Cycle {
+                start: 3,
+                length: 4
+            }
+        );
+        assert_eq!(
+            brent(&5, step),
+            Cycle {
+                start: 0,
+                length: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_with_history() {
+        let (cycle, history) = find_with_history(0, step);
+        assert_eq!(
+            cycle,
+            Cycle {
+                start: 3,
+                length: 4
+            }
+        );
+        assert_eq!(history, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(history[cycle.normalize(1_000_000)], 4);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        for n in 0..20 {
+            let expected = (0..n).fold(0, |x, _| step(&x));
+            assert_eq!(fast_forward(0, step, n), expected);
+        }
+        assert_eq!(fast_forward(0, step, 1_000_000_000), 4);
+    }
+}
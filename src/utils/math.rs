@@ -0,0 +1,310 @@
+#![allow(clippy::cast_possible_truncation)]
+
+/// Extended euclidean algorithm.
+///
+/// Returns `(g, x, y)` with `g = gcd(a, b) >= 0` and `a * x + b * y = g`.
+#[must_use]
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_rem, mut rem) = (a, b);
+    let (mut old_x, mut coef_x) = (1, 0);
+    let (mut old_y, mut coef_y) = (0, 1);
+    while rem != 0 {
+        let quotient = old_rem / rem;
+        (old_rem, rem) = (rem, old_rem - quotient * rem);
+        (old_x, coef_x) = (coef_x, old_x - quotient * coef_x);
+        (old_y, coef_y) = (coef_y, old_y - quotient * coef_y);
+    }
+    if old_rem < 0 {
+        (-old_rem, -old_x, -old_y)
+    } else {
+        (old_rem, old_x, old_y)
+    }
+}
+
+#[must_use]
+pub fn gcd(a: isize, b: isize) -> isize {
+    extended_gcd(a as i128, b as i128).0 as isize
+}
+
+#[must_use]
+pub fn lcm(a: isize, b: isize) -> isize {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        (a / gcd(a, b) * b).abs()
+    }
+}
+
+/// Wraps `value` into `0..modulus`, also for negative values.
+///
+/// # Panics
+///
+/// Panics if `modulus` is zero.
+#[must_use]
+pub fn wrap(value: isize, modulus: isize) -> isize {
+    value.rem_euclid(modulus)
+}
+
+/// `(a * b) mod modulus` without overflowing for large factors.
+///
+/// # Panics
+///
+/// Panics if `modulus` is zero.
+#[must_use]
+pub fn mul_mod(a: isize, b: isize, modulus: isize) -> isize {
+    (a as i128 * b as i128).rem_euclid(modulus as i128) as isize
+}
+
+/// Modular inverse of `a`, if `a` and `modulus` are coprime and the modulus
+/// is positive.
+#[must_use]
+pub fn mod_inverse(a: isize, modulus: isize) -> Option<isize> {
+    if modulus <= 0 {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(wrap(a, modulus) as i128, modulus as i128);
+    // |x| is below the modulus, so it fits
+    (g == 1).then(|| wrap(x as isize, modulus))
+}
+
+/// Chinese remainder theorem for `x = residue (mod modulus)` congruences.
+///
+/// Moduli don't need to be coprime. Returns the smallest non-negative
+/// solution together with the combined modulus, or `None` if the
+/// congruences contradict each other, a modulus isn't positive or the
+/// combined modulus doesn't fit an `i128`.
+#[must_use]
+pub fn crt(congruences: &[(i128, i128)]) -> Option<(i128, i128)> {
+    let mut result: i128 = 0;
+    let mut modulus: i128 = 1;
+    for &(residue, m) in congruences {
+        if m <= 0 {
+            return None;
+        }
+        let residue = residue.rem_euclid(m);
+        let (g, x, _) = extended_gcd(modulus, m);
+        let diff = residue - result;
+        if diff % g != 0 {
+            return None;
+        }
+        let step = m / g;
+        let factor = (diff / g % step).checked_mul(x)?.rem_euclid(step);
+        modulus = modulus.checked_mul(step)?;
+        result = (result + (modulus / step) * factor).rem_euclid(modulus);
+    }
+    Some((result, modulus))
+}
+
+/// Result of solving a 2x2 linear system over the integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinearSolution {
+    /// The only solution, which is integral.
+    Unique(i128, i128),
+    /// The only solution isn't integral.
+    NotIntegral,
+    /// The matrix is singular, so there are either none or infinitely many
+    /// solutions.
+    Singular,
+}
+
+/// Solves `matrix * (x, y) = rhs` exactly with Cramer's rule.
+#[must_use]
+pub fn solve_2x2(matrix: [[i128; 2]; 2], rhs: [i128; 2]) -> LinearSolution {
+    let det = matrix[0][0] * matrix[1][1] - matrix[0][1] * matrix[1][0];
+    if det == 0 {
+        return LinearSolution::Singular;
+    }
+    let det_x = rhs[0] * matrix[1][1] - matrix[0][1] * rhs[1];
+    let det_y = matrix[0][0] * rhs[1] - rhs[0] * matrix[1][0];
+    if det_x % det != 0 || det_y % det != 0 {
+        return LinearSolution::NotIntegral;
+    }
+    LinearSolution::Unique(det_x / det, det_y / det)
+}
+
+/// Cheapest non-negative `(a, b)` with `a * u + b * v = target`.
+///
+/// `u` and `v` have to be non-negative. Every `a` costs `cost_a` and every
+/// `b` costs `cost_b`, `limits` optionally caps `a` and `b`. Returns `None` if
+/// there is no such combination.
+#[must_use]
+pub fn cheapest_combination(
+    u: isize,
+    v: isize,
+    target: isize,
+    (cost_a, cost_b): (isize, isize),
+    (limit_a, limit_b): (Option<isize>, Option<isize>),
+) -> Option<(isize, isize)> {
+    let within_limits = |a: isize, b: isize| {
+        a >= 0 && b >= 0 && limit_a.is_none_or(|l| a <= l) && limit_b.is_none_or(|l| b <= l)
+    };
+    match (u, v) {
+        (0, 0) => (target == 0).then_some((0, 0)),
+        (0, _) => (target % v == 0 && within_limits(0, target / v)).then(|| (0, target / v)),
+        (_, 0) => (target % u == 0 && within_limits(target / u, 0)).then(|| (target / u, 0)),
+        _ => {
+            let (g, coef_u, coef_v) = extended_gcd(u as i128, v as i128);
+            let (u, v, target) = (u as i128, v as i128, target as i128);
+            if target % g != 0 {
+                return None;
+            }
+            let scale = target / g;
+            let (a0, b0) = (coef_u * scale, coef_v * scale);
+            let (step_a, step_b) = (v / g, u / g);
+            // a = a0 + k * step_a and b = b0 - k * step_b have to stay in
+            // 0..=limit_a and 0..=limit_b
+            let mut k_min = -a0.div_euclid(step_a);
+            let mut k_max = b0.div_euclid(step_b);
+            if let Some(limit_a) = limit_a {
+                k_max = k_max.min((limit_a as i128 - a0).div_euclid(step_a));
+            }
+            if let Some(limit_b) = limit_b {
+                k_min = k_min.max(-(limit_b as i128 - b0).div_euclid(step_b));
+            }
+            if k_min > k_max {
+                return None;
+            }
+            let slope = cost_a as i128 * step_a - cost_b as i128 * step_b;
+            let k = if slope < 0 { k_max } else { k_min };
+            Some(((a0 + k * step_a) as isize, (b0 - k * step_b) as isize))
+        }
+    }
+}
+
+/// Number of decimal digits, `0` counts as one digit.
+#[must_use]
+pub fn digit_count(n: usize) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Writes the digits of `b` behind the digits of `a` (`12 || 345 = 12345`).
+#[must_use]
+pub fn concat(a: usize, b: usize) -> usize {
+    a * 10usize.pow(digit_count(b)) + b
+}
+
+/// Splits a number with an even digit count into its left and right half
+/// (`1234 -> (12, 34)`).
+#[must_use]
+pub fn split_digits(n: usize) -> Option<(usize, usize)> {
+    let digits = digit_count(n);
+    if digits.is_multiple_of(2) {
+        let factor = 10usize.pow(digits / 2);
+        Some((n / factor, n % factor))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_gcd() {
+        assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+        assert_eq!(extended_gcd(-240, 46).0, 2);
+        assert_eq!(lcm(101, 103), 10_403);
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(101, 103), Some(51));
+        assert_eq!(mod_inverse(-2, 7), Some(3));
+        assert_eq!(mod_inverse(6, 9), None);
+        assert_eq!(mod_inverse(1, 0), None);
+        assert_eq!(mod_inverse(3, -7), None);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap(-1, 101), 100);
+        assert_eq!(
+            mul_mod(-3, 1_000_000_000_000_000, 101),
+            wrap(-3 * wrap(1_000_000_000_000_000, 101), 101)
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(
+            crt(&[(30, 101), (47, 103)]).map(|(x, _)| (x % 101, x % 103)),
+            Some((30, 47))
+        );
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[(1, 4), (0, 0)]), None);
+        assert_eq!(crt(&[(1, -4)]), None);
+        // The combined modulus doesn't fit an isize
+        let big = 1 << 40;
+        assert_eq!(
+            crt(&[(1, big + 1), (2, big + 3)]),
+            Some((604_462_909_808_963_854_794_754, (big + 1) * (big + 3)))
+        );
+        assert_eq!(crt(&[(0, i128::MAX), (0, i128::MAX - 1)]), None);
+    }
+
+    #[test]
+    fn test_solve_2x2() {
+        assert_eq!(
+            solve_2x2([[94, 22], [34, 67]], [8400, 5400]),
+            LinearSolution::Unique(80, 40)
+        );
+        assert_eq!(
+            solve_2x2([[26, 67], [66, 21]], [12748, 12176]),
+            LinearSolution::NotIntegral
+        );
+        assert_eq!(
+            solve_2x2([[1, 2], [2, 4]], [3, 6]),
+            LinearSolution::Singular
+        );
+    }
+
+    #[test]
+    fn test_cheapest_combination() {
+        let unlimited = (None, None);
+        assert_eq!(
+            cheapest_combination(2, 4, 10, (3, 1), unlimited),
+            Some((1, 2))
+        );
+        assert_eq!(
+            cheapest_combination(2, 4, 10, (1, 3), unlimited),
+            Some((5, 0))
+        );
+        assert_eq!(cheapest_combination(4, 6, 7, (3, 1), unlimited), None);
+        assert_eq!(
+            cheapest_combination(0, 5, 10, (3, 1), unlimited),
+            Some((0, 2))
+        );
+        assert_eq!(cheapest_combination(0, 0, 1, (3, 1), unlimited), None);
+        assert_eq!(cheapest_combination(3, 5, 1, (3, 1), unlimited), None);
+        assert_eq!(
+            cheapest_combination(2, 4, 10, (1, 3), (Some(3), None)),
+            Some((3, 1))
+        );
+        assert_eq!(
+            cheapest_combination(2, 4, 10, (3, 1), (None, Some(1))),
+            Some((3, 1))
+        );
+        assert_eq!(
+            cheapest_combination(2, 4, 10, (3, 1), (Some(2), Some(1))),
+            None
+        );
+        assert_eq!(
+            cheapest_combination(0, 5, 10, (3, 1), (None, Some(1))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(digit_count(0), 1);
+        assert_eq!(digit_count(1000), 4);
+        assert_eq!(concat(12, 345), 12_345);
+        assert_eq!(concat(12, 0), 120);
+        assert_eq!(split_digits(1234), Some((12, 34)));
+        assert_eq!(split_digits(1000), Some((10, 0)));
+        assert_eq!(split_digits(123), None);
+    }
+}